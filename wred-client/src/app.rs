@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{cmp::Ordering, collections::HashMap};
 
 use egui::{
     collapsing_header::CollapsingState, Align, Button, CentralPanel, Color32, ComboBox, Frame,
@@ -7,12 +7,92 @@ use egui::{
 use poll_promise::Promise;
 use sequence_generator::sequence_generator;
 use serde::{Deserialize, Serialize};
+use wred_server::{
+    BulkResult, HistoryEntry, LogEntryPartial, LogEvent, LogMeta, LogMetaPatch, Regression,
+    RegressionSummary, Selection, Severity, Status, Submitter, TriageChange,
+};
+
+use crate::utils::Fetch;
+
+/// How long links from the share button work for.
+const SHARE_EXPIRY: std::time::Duration = std::time::Duration::from_secs(7 * 24 * 60 * 60);
+
+/// How long to wait before reconnecting a lost live tail, doubled for each attempt in a row
+/// that fails, up to `2^MAX_STREAM_BACKOFF` times as long.
+const STREAM_RETRY_DELAY: f64 = 1.0;
+const MAX_STREAM_BACKOFF: u32 = 5;
+
+/// Formats logs can be downloaded in, with what to call them.
+const DOWNLOAD_FORMATS: [(&str, &str); 4] = [
+    ("text", "Plain text"),
+    ("gzip", "Gzipped text"),
+    ("jsonl", "JSON lines"),
+    ("html", "HTML report"),
+];
 
 #[derive(Deserialize, Serialize, Copy, Clone, PartialEq, Eq)]
 pub enum SortBy {
     CreationDate,
     IPAddress,
     LastUpdated,
+    Status,
+    Gpu,
+    OsVersion,
+}
+
+impl SortBy {
+    /// Heading of the group a log is in, for sorts that group logs. `None` if the log lacks the
+    /// facts to group it by.
+    fn group(self, ent: &LogEntryPartial) -> Option<String> {
+        match self {
+            Self::Gpu => {
+                let device_id = ent.facts.get("device_id")?;
+                let device_id = device_id.strip_prefix("0x").unwrap_or(device_id);
+                let device_id = format!("0x{}", device_id.to_ascii_lowercase());
+                Some(match ent.facts.get("revision") {
+                    Some(revision) => format!("{device_id} rev {}", revision.to_ascii_lowercase()),
+                    None => device_id,
+                })
+            }
+            Self::OsVersion => Some(format!("macOS {}", ent.facts.get("os_version")?)),
+            _ => None,
+        }
+    }
+
+    const fn groups(self) -> bool {
+        matches!(self, Self::Gpu | Self::OsVersion)
+    }
+
+    /// Orders logs by group, leaving those without one last either way.
+    fn cmp_groups(self, a: &LogEntryPartial, b: &LogEntryPartial, ascending: bool) -> Ordering {
+        match (self.group(a), self.group(b)) {
+            (Some(a), Some(b)) => {
+                let by_group = if self == Self::OsVersion {
+                    cmp_versions(&a, &b)
+                } else {
+                    a.cmp(&b)
+                };
+                if ascending {
+                    by_group
+                } else {
+                    by_group.reverse()
+                }
+            }
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+}
+
+/// Compares versions like `macOS 13.2.1` part by part, numerically where the parts are numbers.
+fn cmp_versions(a: &str, b: &str) -> Ordering {
+    let parts = |v: &str| {
+        v.split(|c: char| c == '.' || c.is_whitespace())
+            .map(|v| v.parse::<u64>().map_err(|_| v.to_owned()))
+            .collect::<Vec<_>>()
+    };
+    parts(a).cmp(&parts(b))
 }
 
 impl ToString for SortBy {
@@ -21,24 +101,350 @@ impl ToString for SortBy {
             Self::IPAddress => "IP Address",
             Self::CreationDate => "Creation Date",
             Self::LastUpdated => "Last Updated",
+            Self::Status => "Status",
+            Self::Gpu => "GPU",
+            Self::OsVersion => "macOS Version",
         }
         .to_owned()
     }
 }
 
+/// Whether a log is a baseline, or how it compares with its baseline.
+fn show_baseline_badge(ui: &mut egui::Ui, ent: &LogEntryPartial) {
+    let key = ent
+        .key
+        .as_ref()
+        .map(ToString::to_string)
+        .unwrap_or_default();
+    if ent.is_baseline {
+        ui.colored_label(Color32::LIGHT_GREEN, "\u{1F4CF} Baseline")
+            .on_hover_text(key);
+        return;
+    }
+    let Some(summary) = ent.regression else {
+        return;
+    };
+    let baseline = wred_server::session_code(summary.baseline);
+    let new = summary.errors + summary.warnings;
+    if new == 0 {
+        ui.weak("\u{2714} Matches baseline").on_hover_text(format!(
+            "No new errors or warnings compared with {baseline} ({key})"
+        ));
+        return;
+    }
+    let color = if summary.errors > 0 {
+        Color32::LIGHT_RED
+    } else {
+        Color32::YELLOW
+    };
+    ui.colored_label(color, format!("\u{26A0} {new} new"))
+        .on_hover_text(format!(
+            "{} new errors and {} new warnings compared with {baseline} ({key})",
+            summary.errors, summary.warnings
+        ));
+}
+
+/// Counts of lines the server's rules flagged, colored by severity. Returns the label of the
+/// badge that was clicked, if one was.
+fn show_flag_badges(ui: &mut egui::Ui, ent: &LogEntryPartial) -> Option<String> {
+    let mut clicked = None;
+    for flag in &ent.flags {
+        let color = match flag.severity {
+            Severity::Error => Color32::LIGHT_RED,
+            Severity::Warning => Color32::YELLOW,
+        };
+        let badge = Label::new(
+            RichText::new(format!("{} {}", flag.label, flag.count))
+                .small()
+                .color(color),
+        )
+        .sense(Sense::click());
+        if ui
+            .add(badge)
+            .on_hover_text(format!(
+                "{} lines flagged {:?}. Click to show logs with this flag",
+                flag.count, flag.label
+            ))
+            .clicked()
+        {
+            clicked = Some(flag.label.clone());
+        }
+    }
+    clicked
+}
+
+/// The GPU and macOS version a log printed, with every fact on hover.
+fn show_facts(ui: &mut egui::Ui, ent: &LogEntryPartial) {
+    if ent.facts.is_empty() {
+        return;
+    }
+    let summary = [SortBy::Gpu, SortBy::OsVersion]
+        .into_iter()
+        .filter_map(|v| v.group(ent))
+        .collect::<Vec<_>>()
+        .join(" \u{B7} ");
+    let all = ent
+        .facts
+        .iter()
+        .map(|(k, v)| format!("{k}: {v}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let summary = if summary.is_empty() {
+        "\u{2139}".to_owned()
+    } else {
+        summary
+    };
+    ui.label(RichText::new(summary).small().weak())
+        .on_hover_text(all);
+}
+
+fn fetch_regression(
+    base_url: &str,
+    id: u64,
+    summary: RegressionSummary,
+    token: &str,
+    ctx: &egui::Context,
+) -> (RegressionSummary, Fetch<Regression>) {
+    let (sender, promise) = Promise::new();
+    crate::requests::get_regression(base_url, id, token, sender, ctx.clone());
+    (summary, promise)
+}
+
+/// Lists the error and warning lines the log's baseline doesn't have.
+fn show_regression(ui: &mut egui::Ui, regression: &Regression) {
+    if regression.lines.is_empty() {
+        return;
+    }
+    Frame::group(ui.style()).show(ui, |ui| {
+        ui.label(
+            RichText::new(format!(
+                "New compared with baseline {}",
+                wred_server::session_code(regression.baseline)
+            ))
+            .strong(),
+        );
+        for line in &regression.lines {
+            let color = match line.severity {
+                Severity::Error => Color32::LIGHT_RED,
+                Severity::Warning => Color32::YELLOW,
+            };
+            ui.horizontal(|ui| {
+                ui.label(
+                    RichText::new(format!("{:>5}", line.number))
+                        .monospace()
+                        .weak(),
+                );
+                ui.label(RichText::new(&line.text).monospace().color(color));
+            });
+        }
+    });
+}
+
+/// A log's metadata and triage state while it's being edited, as typed.
+struct MetaDraft {
+    title: String,
+    notes: String,
+    tags: String,
+    /// For [`Status::DuplicateOf`], whose ID comes from `duplicate_of` instead.
+    status: Status,
+    duplicate_of: String,
+    assignee: String,
+}
+
+impl MetaDraft {
+    fn new(ent: &LogEntryPartial) -> Self {
+        Self {
+            title: ent.meta.title.clone(),
+            notes: ent.meta.notes.clone(),
+            tags: ent.meta.tags.iter().cloned().collect::<Vec<_>>().join(", "),
+            status: ent.status,
+            duplicate_of: match ent.status {
+                Status::DuplicateOf(id) => wred_server::session_code(id),
+                _ => String::new(),
+            },
+            assignee: ent.assignee.clone().unwrap_or_default(),
+        }
+    }
+
+    /// The chosen status, if the log it's a duplicate of, given by ID or code, is one of `ids`.
+    fn status(&self, ids: &[u64]) -> Option<Status> {
+        let Status::DuplicateOf(_) = self.status else {
+            return Some(self.status);
+        };
+        let v = self.duplicate_of.trim();
+        let code = wred_server::parse_session_code(v);
+        ids.iter()
+            .copied()
+            .find(|&id| {
+                v.parse() == Ok(id) || code.as_ref() == Some(&wred_server::session_code(id))
+            })
+            .map(Status::DuplicateOf)
+    }
+
+    /// Shows the editor. Returns `Some(true)` to apply the changes and `Some(false)` to discard
+    /// them, closing the popup either way.
+    fn show(&mut self, ui: &mut egui::Ui, id: u64, ids: &[u64], can_triage: bool) -> Option<bool> {
+        ui.set_min_width(250.0);
+        ui.add(TextEdit::singleline(&mut self.title).hint_text("Title"));
+        ui.add(TextEdit::singleline(&mut self.tags).hint_text("Tags, comma-separated"));
+        ui.add(TextEdit::multiline(&mut self.notes).hint_text("Notes"));
+        ui.add_enabled_ui(can_triage, |ui| {
+            ComboBox::from_id_source(("status", id))
+                .selected_text(match self.status {
+                    Status::DuplicateOf(_) => "Duplicate of".to_owned(),
+                    v => v.to_string(),
+                })
+                .show_ui(ui, |ui| {
+                    for v in [
+                        Status::New,
+                        Status::Triaging,
+                        Status::NeedsInfo,
+                        Status::Resolved,
+                    ] {
+                        ui.selectable_value(&mut self.status, v, v.to_string());
+                    }
+                    let is_duplicate = matches!(self.status, Status::DuplicateOf(_));
+                    if ui.selectable_label(is_duplicate, "Duplicate of").clicked() {
+                        self.status = Status::DuplicateOf(0);
+                    }
+                });
+            if let Status::DuplicateOf(_) = self.status {
+                ui.add(TextEdit::singleline(&mut self.duplicate_of).hint_text("Code or ID"));
+            }
+            ui.add(TextEdit::singleline(&mut self.assignee).hint_text("Assignee"));
+        });
+
+        let mut ret = None;
+        ui.horizontal(|ui| {
+            let can_apply = self.status(ids).is_some();
+            if ui.add_enabled(can_apply, Button::new("Apply")).clicked() {
+                ret = Some(true);
+            }
+            if ui.button("Cancel").clicked() {
+                ret = Some(false);
+            }
+        });
+        if ret.is_some() {
+            ui.memory().close_popup();
+        }
+        ret
+    }
+
+    /// Sends the changes, only touching the triage state where it changed.
+    fn submit(
+        &self,
+        ent: &LogEntryPartial,
+        ids: &[u64],
+        can_triage: bool,
+        base_url: &str,
+        token: &str,
+        ctx: &egui::Context,
+    ) -> (Fetch<LogMeta>, Vec<Fetch<HistoryEntry>>) {
+        let (sender, meta) = Promise::new();
+        crate::requests::patch_log(
+            base_url,
+            ent.id,
+            token,
+            &self.to_patch(),
+            sender,
+            ctx.clone(),
+        );
+
+        let mut triage = Vec::new();
+        if !can_triage {
+            return (meta, triage);
+        }
+        if let Some(status) = self.status(ids).filter(|&v| v != ent.status) {
+            let (sender, promise) = Promise::new();
+            crate::requests::set_status(base_url, ent.id, token, status, sender, ctx.clone());
+            triage.push(promise);
+        }
+        let assignee = Some(self.assignee.trim().to_owned()).filter(|v| !v.is_empty());
+        if assignee != ent.assignee {
+            let (sender, promise) = Promise::new();
+            crate::requests::set_assignee(base_url, ent.id, token, assignee, sender, ctx.clone());
+            triage.push(promise);
+        }
+        (meta, triage)
+    }
+
+    fn to_patch(&self) -> LogMetaPatch {
+        LogMetaPatch {
+            title: Some(self.title.trim().to_owned()),
+            notes: Some(self.notes.trim().to_owned()),
+            tags: Some(
+                self.tags
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|v| !v.is_empty())
+                    .map(str::to_owned)
+                    .collect(),
+            ),
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct WRedNetDbgApp {
     base_url: String,
-    secret: String,
+    #[serde(alias = "secret")]
+    token: String,
     show_ips: bool,
     show_base: bool,
     sort_by: SortBy,
     sort_ascending: bool,
+    follow: bool,
+    #[serde(default)]
+    filter: String,
+    #[serde(skip)]
+    stream: Option<crate::stream::LogStream>,
+    /// When to reconnect the live tail after losing it, and how many attempts in a row failed.
+    #[serde(skip)]
+    stream_retry: Option<(f64, u32)>,
+    #[serde(skip)]
+    deferred_events: Vec<LogEvent>,
+    #[serde(skip)]
+    server_info: Option<Promise<Result<wred_server::ServerInfo, String>>>,
     #[serde(skip)]
     log_cache: HashMap<u64, Promise<Result<String, String>>>,
+    /// Length of each open log when it was last shown, to tell when it grows.
+    #[serde(skip)]
+    shown_lens: HashMap<u64, usize>,
     #[serde(skip)]
     log_cache_ents: Option<Promise<Result<Vec<wred_server::LogEntryPartial>, String>>>,
     #[serde(skip)]
+    share_link: Option<Promise<Result<wred_server::ShareLink, String>>>,
+    #[serde(skip)]
+    comments: crate::comments::Comments,
+    #[serde(skip)]
+    lines: crate::lines::Lines,
+    #[serde(skip)]
+    known_issues: crate::known_issues::KnownIssues,
+    #[serde(skip)]
+    diff: crate::diff::DiffView,
+    #[serde(skip)]
+    meta_drafts: HashMap<u64, MetaDraft>,
+    #[serde(skip)]
+    meta_updates: Vec<(u64, Promise<Result<LogMeta, String>>)>,
+    #[serde(skip)]
+    triage_updates: Vec<(u64, Promise<Result<HistoryEntry, String>>)>,
+    /// Bulk operation in flight, with what to call it if some logs fail.
+    #[serde(skip)]
+    bulk: Option<(String, Fetch<Vec<BulkResult>>)>,
+    /// What went wrong with the last bulk operation or other change, until dismissed.
+    #[serde(skip)]
+    report: Option<String>,
+    #[serde(skip)]
+    baseline_update: Option<Fetch<()>>,
+    /// Requests whose outcome only matters if they fail, with what to call them.
+    #[serde(skip)]
+    actions: Vec<(String, Fetch<()>)>,
+    /// Fetched for the summary they were fetched for, so they're refetched when it changes.
+    #[serde(skip)]
+    regressions: HashMap<u64, (RegressionSummary, Fetch<Regression>)>,
+    #[serde(skip)]
+    bulk_tag: String,
+    #[serde(skip)]
     formatter: timeago::Formatter,
 }
 
@@ -46,13 +452,34 @@ impl Default for WRedNetDbgApp {
     fn default() -> Self {
         Self {
             base_url: crate::utils::base_url(),
-            secret: String::new(),
+            token: String::new(),
             show_ips: true,
             show_base: false,
             sort_by: SortBy::CreationDate,
             sort_ascending: false,
+            follow: false,
+            filter: String::new(),
+            stream: None,
+            stream_retry: None,
+            deferred_events: Vec::new(),
+            server_info: None,
             log_cache: HashMap::default(),
+            shown_lens: HashMap::default(),
             log_cache_ents: None,
+            share_link: None,
+            comments: crate::comments::Comments::default(),
+            lines: crate::lines::Lines::default(),
+            known_issues: crate::known_issues::KnownIssues::default(),
+            diff: crate::diff::DiffView::default(),
+            meta_drafts: HashMap::default(),
+            meta_updates: Vec::new(),
+            triage_updates: Vec::new(),
+            bulk: None,
+            report: None,
+            baseline_update: None,
+            actions: Vec::new(),
+            regressions: HashMap::default(),
+            bulk_tag: String::new(),
             formatter: timeago::Formatter::with_language(timeago::English),
         }
     }
@@ -66,6 +493,295 @@ impl WRedNetDbgApp {
             eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default()
         })
     }
+
+    /// Turns a fetched log into a fetch of whatever was appended to it since.
+    fn resume_log(
+        &self,
+        id: u64,
+        promise: Promise<Result<String, String>>,
+        ctx: &egui::Context,
+    ) -> Option<Promise<Result<String, String>>> {
+        let data = promise.try_take().ok()?.ok()?;
+        let (sender, promise) = Promise::new();
+        crate::requests::get_log(&self.base_url, id, &self.token, data, sender, ctx.clone());
+        Some(promise)
+    }
+
+    fn supports(&self, capability: &str) -> bool {
+        matches!(
+            self.server_info.as_ref().and_then(Promise::ready),
+            Some(Ok(info)) if info.supports(capability)
+        )
+    }
+
+    fn refresh(&mut self, ctx: &egui::Context) {
+        self.log_cache = std::mem::take(&mut self.log_cache)
+            .into_iter()
+            .filter_map(|(id, promise)| Some((id, self.resume_log(id, promise, ctx)?)))
+            .collect();
+        self.log_cache_ents = None;
+        self.server_info = None;
+        self.comments.clear();
+        self.known_issues.clear();
+    }
+
+    fn handle_events(&mut self, ctx: &egui::Context) {
+        if !self.follow || !self.supports(wred_server::capability::STREAM) {
+            self.stream = None;
+            self.stream_retry = None;
+            self.deferred_events.clear();
+            return;
+        }
+        let now = ctx.input().time;
+        if let Some((at, _)) = self.stream_retry.filter(|_| self.stream.is_none()) {
+            if now < at {
+                ctx.request_repaint_after(std::time::Duration::from_secs_f64(at - now));
+                return;
+            }
+        }
+        let stream = self.stream.get_or_insert_with(|| {
+            // After losing the connection, fetch whatever was missed once it's back.
+            let catch_up = self.stream_retry.is_some();
+            crate::stream::LogStream::open(&self.base_url, &self.token, catch_up, ctx.clone())
+        });
+        let mut events = std::mem::take(&mut self.deferred_events);
+        let mut lost = None;
+        for v in stream.events() {
+            match v {
+                Ok(v) => {
+                    events.push(v);
+                    self.stream_retry = None;
+                }
+                Err(e) => lost = Some(e),
+            }
+        }
+        if let Some(e) = lost {
+            self.stream = None;
+            let attempts = self.stream_retry.map_or(0, |(_, v)| v + 1);
+            let delay = STREAM_RETRY_DELAY * f64::from(1 << attempts.min(MAX_STREAM_BACKOFF));
+            self.stream_retry = Some((now + delay, attempts));
+            self.report = Some(format!("Live tail lost, reconnecting: {e}"));
+            ctx.request_repaint_after(std::time::Duration::from_secs_f64(delay));
+        }
+
+        for event in events {
+            let ents = self
+                .log_cache_ents
+                .as_mut()
+                .and_then(Promise::ready_mut)
+                .and_then(|v| v.as_mut().ok());
+            match event {
+                LogEvent::Session(ent) => {
+                    if let Some(ents) = ents {
+                        if let Some(v) = ents.iter_mut().find(|v| v.id == ent.id) {
+                            *v = ent;
+                        } else {
+                            ents.push(ent);
+                        }
+                    }
+                }
+                LogEvent::Append {
+                    id,
+                    offset,
+                    last_updated,
+                    ref data,
+                } => {
+                    if let Some(v) = ents.and_then(|v| v.iter_mut().find(|v| v.id == id)) {
+                        v.last_updated = last_updated;
+                    }
+                    let Some(promise) = self.log_cache.get_mut(&id) else {
+                        continue;
+                    };
+                    match promise.ready_mut() {
+                        // A fetch in flight may or may not include this yet.
+                        None => self.deferred_events.push(event),
+                        Some(Ok(log)) if log.len() as u64 == offset => log.push_str(data),
+                        Some(Ok(log)) if (log.len() as u64) < offset => {
+                            let promise = self.log_cache.remove(&id).unwrap();
+                            if let Some(promise) = self.resume_log(id, promise, ctx) {
+                                self.log_cache.insert(id, promise);
+                            }
+                        }
+                        Some(_) => {}
+                    }
+                }
+                LogEvent::Removed(id) => {
+                    if let Some(ents) = ents {
+                        ents.retain(|v| v.id != id);
+                    }
+                    self.log_cache.remove(&id);
+                    self.shown_lens.remove(&id);
+                    self.comments.forget(id);
+                    self.lines.forget(id);
+                }
+                LogEvent::Comments(id) => self.comments.refetch(id),
+                LogEvent::Lagged => self.refresh(ctx),
+            }
+        }
+    }
+
+    fn cached_ent(&mut self, id: u64) -> Option<&mut LogEntryPartial> {
+        self.log_cache_ents
+            .as_mut()
+            .and_then(Promise::ready_mut)
+            .and_then(|v| v.as_mut().ok())
+            .and_then(|v| v.iter_mut().find(|v| v.id == id))
+    }
+
+    /// Applies metadata and triage edits the server has confirmed to the cached summaries.
+    fn handle_updates(&mut self) {
+        for (id, promise) in std::mem::take(&mut self.meta_updates) {
+            match promise.try_take() {
+                Err(promise) => self.meta_updates.push((id, promise)),
+                Ok(Ok(meta)) => {
+                    if let Some(v) = self.cached_ent(id) {
+                        v.meta = meta;
+                    }
+                }
+                Ok(Err(e)) => {
+                    self.report = Some(format!("Updating the title, tags or notes failed: {e}"))
+                }
+            }
+        }
+        for (id, promise) in std::mem::take(&mut self.triage_updates) {
+            match promise.try_take() {
+                Err(promise) => self.triage_updates.push((id, promise)),
+                Ok(Ok(entry)) => {
+                    if let Some(v) = self.cached_ent(id) {
+                        match entry.change {
+                            TriageChange::Status(status) => v.status = status,
+                            TriageChange::Assignee(assignee) => v.assignee = assignee,
+                        }
+                    }
+                }
+                Ok(Err(e)) => {
+                    self.report = Some(format!("Changing the status or assignee failed: {e}"));
+                }
+            }
+        }
+        for (name, promise) in std::mem::take(&mut self.actions) {
+            match promise.try_take() {
+                Err(promise) => self.actions.push((name, promise)),
+                Ok(result) => {
+                    if let Err(e) = result {
+                        self.report = Some(format!("{name} failed: {e}"));
+                    }
+                    self.log_cache_ents = None;
+                }
+            }
+        }
+    }
+
+    fn show_bulk_menu(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        let shown = Selection::Ids(self.shown_ids());
+        if ui.button("Save").clicked() {
+            self.start_bulk("save", "Saving", &shown, ctx);
+            ui.close_menu();
+        }
+        ui.menu_button("Set status", |ui| {
+            for status in [
+                Status::New,
+                Status::Triaging,
+                Status::NeedsInfo,
+                Status::Resolved,
+            ] {
+                if ui.button(status.to_string()).clicked() {
+                    let body = wred_server::BulkStatus {
+                        select: shown.clone(),
+                        status,
+                    };
+                    self.start_bulk("status", "Setting status", &body, ctx);
+                    ui.close_menu();
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.add(
+                TextEdit::singleline(&mut self.bulk_tag)
+                    .desired_width(100.0)
+                    .hint_text("Tag"),
+            );
+            let tag = self.bulk_tag.trim().to_owned();
+            let mut body = wred_server::BulkTags {
+                select: shown.clone(),
+                add: std::collections::BTreeSet::new(),
+                remove: std::collections::BTreeSet::new(),
+            };
+            if ui
+                .add_enabled(!tag.is_empty(), Button::new("Add"))
+                .clicked()
+            {
+                body.add.insert(tag);
+                self.start_bulk("tag", "Tagging", &body, ctx);
+                ui.close_menu();
+            } else if ui
+                .add_enabled(!tag.is_empty(), Button::new("Remove"))
+                .clicked()
+            {
+                body.remove.insert(tag);
+                self.start_bulk("tag", "Untagging", &body, ctx);
+                ui.close_menu();
+            }
+        });
+    }
+
+    /// IDs of the logs the filter lets through.
+    fn shown_ids(&self) -> Vec<u64> {
+        let filter = self.filter.parse::<wred_server::query::Query>();
+        match self.log_cache_ents.as_ref().and_then(Promise::ready) {
+            Some(Ok(ents)) => ents
+                .iter()
+                .filter(|v| filter.as_ref().map_or(true, |f| f.matches(v)))
+                .map(|v| v.id)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Starts a bulk operation, unless one is already in flight.
+    fn start_bulk<T: Serialize>(&mut self, op: &str, name: &str, body: &T, ctx: &egui::Context) {
+        if self.bulk.is_some() {
+            return;
+        }
+        let (sender, promise) = Promise::new();
+        crate::requests::bulk(&self.base_url, op, &self.token, body, sender, ctx.clone());
+        self.bulk = Some((name.to_owned(), promise));
+    }
+
+    /// Refetches the summaries once a bulk operation lands, and reports the logs it failed for.
+    fn handle_bulk(&mut self) {
+        let Some((name, promise)) = self.bulk.take() else {
+            return;
+        };
+        let result = match promise.try_take() {
+            Err(promise) => {
+                self.bulk = Some((name, promise));
+                return;
+            }
+            Ok(v) => v,
+        };
+        self.log_cache_ents = None;
+        self.report = match result {
+            Err(e) => Some(format!("{name} failed: {e}")),
+            Ok(results) => {
+                let failed = results
+                    .iter()
+                    .filter_map(|v| {
+                        let e = v.error.as_ref()?;
+                        Some(format!("{} ({e})", wred_server::session_code(v.id)))
+                    })
+                    .collect::<Vec<_>>();
+                (!failed.is_empty()).then(|| {
+                    format!(
+                        "{name} failed for {} of {} logs: {}",
+                        failed.len(),
+                        results.len(),
+                        failed.join(", ")
+                    )
+                })
+            }
+        };
+    }
 }
 
 impl eframe::App for WRedNetDbgApp {
@@ -83,10 +799,10 @@ impl eframe::App for WRedNetDbgApp {
                     ui.add_space(60.0);
 
                     ui.add(
-                        TextEdit::singleline(&mut self.secret)
+                        TextEdit::singleline(&mut self.token)
                             .desired_width(150.0)
                             .password(true)
-                            .hint_text("Admin Secret"),
+                            .hint_text("API Token"),
                     );
 
                     ui.add(
@@ -97,29 +813,91 @@ impl eframe::App for WRedNetDbgApp {
                     );
                     ui.toggle_value(&mut self.show_base, "\u{1F441}");
 
+                    ui.add(
+                        TextEdit::singleline(&mut self.filter)
+                            .desired_width(200.0)
+                            .hint_text("Filter, e.g. tag:ventura -saved:yes"),
+                    );
+
                     ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
                         if ui.button("\u{1F504}").clicked() {
-                            self.log_cache.clear();
-                            self.log_cache_ents = None;
+                            self.refresh(ctx);
+                        }
+                        ui.add_enabled_ui(self.supports(wred_server::capability::STREAM), |ui| {
+                            ui.toggle_value(&mut self.follow, "Follow")
+                                .on_disabled_hover_text("Not supported by this server");
+                        });
+
+                        if ui
+                            .add_enabled(
+                                !self.token.is_empty()
+                                    && self.supports(wred_server::capability::SHARE),
+                                Button::new("Revoke shares"),
+                            )
+                            .on_hover_text("Invalidate every share link handed out so far")
+                            .clicked()
+                        {
+                            let (sender, promise) = Promise::new();
+                            crate::requests::rotate_share_links(
+                                &self.base_url,
+                                &self.token,
+                                sender,
+                                ctx.clone(),
+                            );
+                            self.actions
+                                .push(("Revoking share links".to_owned(), promise));
                         }
 
-                        if ui.button("Discard unsaved").clicked() {
+                        let can_bulk =
+                            !self.token.is_empty() && self.supports(wred_server::capability::BULK);
+                        if ui
+                            .add_enabled(
+                                !self.token.is_empty() && self.bulk.is_none(),
+                                Button::new("Discard unsaved"),
+                            )
+                            .clicked()
+                        {
                             if let Some(Ok(ents)) =
                                 self.log_cache_ents.as_ref().and_then(|v| v.ready())
                             {
-                                for ent in ents.iter().filter(|v| !v.is_saved) {
-                                    self.log_cache.remove(&ent.id);
-                                    crate::requests::delete_log(
-                                        &self.base_url,
-                                        ent.id,
-                                        &self.secret,
-                                        ctx.clone(),
+                                let ids = ents
+                                    .iter()
+                                    .filter(|v| !v.is_saved)
+                                    .map(|v| v.id)
+                                    .collect::<Vec<_>>();
+                                for id in &ids {
+                                    self.log_cache.remove(id);
+                                }
+                                if can_bulk {
+                                    self.start_bulk(
+                                        "delete",
+                                        "Discarding",
+                                        &Selection::Ids(ids),
+                                        ctx,
                                     );
+                                } else {
+                                    for id in ids {
+                                        let (sender, promise) = Promise::new();
+                                        crate::requests::delete_log(
+                                            &self.base_url,
+                                            id,
+                                            &self.token,
+                                            sender,
+                                            ctx.clone(),
+                                        );
+                                        self.actions.push(("Discarding".to_owned(), promise));
+                                    }
                                 }
                             }
                             self.log_cache_ents = None;
                         }
 
+                        ui.add_enabled_ui(can_bulk && self.bulk.is_none(), |ui| {
+                            ui.menu_button("Shown", |ui| self.show_bulk_menu(ui, ctx))
+                                .response
+                                .on_hover_text("Act on every log the filter lets through");
+                        });
+
                         ui.separator();
                         ui.checkbox(&mut self.show_ips, "IPs shown");
                         ui.separator();
@@ -128,9 +906,14 @@ impl eframe::App for WRedNetDbgApp {
                         ComboBox::from_id_source("sort_by")
                             .selected_text(self.sort_by.to_string())
                             .show_ui(ui, |ui| {
-                                for v in
-                                    [SortBy::CreationDate, SortBy::IPAddress, SortBy::LastUpdated]
-                                {
+                                for v in [
+                                    SortBy::CreationDate,
+                                    SortBy::IPAddress,
+                                    SortBy::LastUpdated,
+                                    SortBy::Status,
+                                    SortBy::Gpu,
+                                    SortBy::OsVersion,
+                                ] {
                                     ui.selectable_value(&mut self.sort_by, v, v.to_string());
                                 }
                             });
@@ -140,9 +923,60 @@ impl eframe::App for WRedNetDbgApp {
             });
         });
 
+        self.server_info.get_or_insert_with(|| {
+            let (sender, promise) = Promise::new();
+            crate::requests::get_server_info(&self.base_url, sender, ctx.clone());
+            promise
+        });
+        self.handle_events(ctx);
+        self.handle_updates();
+        self.handle_bulk();
+        if let Some(result) = self.baseline_update.as_ref().and_then(Promise::ready) {
+            if let Err(e) = result {
+                self.report = Some(format!("Changing the baseline failed: {e}"));
+            }
+            self.baseline_update = None;
+            self.log_cache_ents = None;
+        }
+
+        if let Some(result) = self.share_link.as_ref().and_then(Promise::ready) {
+            match result {
+                Ok(link) => ctx.output().copied_text = format!("{}{}", self.base_url, link.path),
+                Err(e) => self.report = Some(format!("Sharing failed: {e}")),
+            }
+            self.share_link = None;
+        }
+
+        if let Some(report) = &self.report {
+            let mut dismissed = false;
+            TopBottomPanel::bottom("report").show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.colored_label(Color32::RED, report);
+                    dismissed = ui.button("Dismiss").clicked();
+                });
+            });
+            if dismissed {
+                self.report = None;
+            }
+        }
+
+        let can_share = !self.token.is_empty() && self.supports(wred_server::capability::SHARE);
+        let can_edit = !self.token.is_empty() && self.supports(wred_server::capability::META);
+        let show_comments = self.supports(wred_server::capability::COMMENTS);
+        let can_download = self.supports(wred_server::capability::DOWNLOAD);
+        let can_baseline =
+            !self.token.is_empty() && self.supports(wred_server::capability::BASELINES);
+        let can_diff = self.supports(wred_server::capability::DIFF);
+        self.diff.show_window(ctx, &self.base_url, &self.token);
+        self.known_issues
+            .show_window(ctx, &self.base_url, &self.token);
+        let can_sign = !self.token.is_empty() && self.supports(wred_server::capability::SIGNATURES);
+        let can_archive = !self.token.is_empty() && self.supports(wred_server::capability::ARCHIVE);
+        let can_triage = !self.token.is_empty() && self.supports(wred_server::capability::TRIAGE);
+        let filter = self.filter.parse::<wred_server::query::Query>();
         let log_cache_ents = self.log_cache_ents.get_or_insert_with(|| {
             let (sender, promise) = Promise::new();
-            crate::requests::get_logs(&self.base_url, sender, ctx.clone());
+            crate::requests::get_logs(&self.base_url, &self.token, sender, ctx.clone());
             promise
         });
 
@@ -155,7 +989,14 @@ impl eframe::App for WRedNetDbgApp {
                     ui.colored_label(Color32::RED, RichText::new(e));
                 }
                 Some(Ok(ents)) => {
-                    let mut ents = ents.iter().collect::<Vec<_>>();
+                    if let Err(e) = &filter {
+                        ui.colored_label(Color32::RED, RichText::new(e));
+                    }
+                    let ids = ents.iter().map(|v| v.id).collect::<Vec<_>>();
+                    let mut ents = ents
+                        .iter()
+                        .filter(|v| filter.as_ref().map_or(true, |f| f.matches(v)))
+                        .collect::<Vec<_>>();
                     ents.sort_by(|a, b| match self.sort_by {
                         SortBy::CreationDate => {
                             if self.sort_ascending {
@@ -166,9 +1007,9 @@ impl eframe::App for WRedNetDbgApp {
                         }
                         SortBy::IPAddress => {
                             if self.sort_ascending {
-                                a.addr.ip().cmp(&b.addr.ip())
+                                a.addr.cmp(&b.addr)
                             } else {
-                                b.addr.ip().cmp(&a.addr.ip())
+                                b.addr.cmp(&a.addr)
                             }
                         }
                         SortBy::LastUpdated => {
@@ -178,11 +1019,42 @@ impl eframe::App for WRedNetDbgApp {
                                 b.last_updated.cmp(&a.last_updated)
                             }
                         }
+                        // Groups by status, newest first within each.
+                        SortBy::Status => {
+                            let by_status = if self.sort_ascending {
+                                a.status.cmp(&b.status)
+                            } else {
+                                b.status.cmp(&a.status)
+                            };
+                            by_status.then(b.id.cmp(&a.id))
+                        }
+                        // Groups by what the log printed, newest first within each.
+                        SortBy::Gpu | SortBy::OsVersion => self
+                            .sort_by
+                            .cmp_groups(a, b, self.sort_ascending)
+                            .then(b.id.cmp(&a.id)),
                     });
+                    let mut last_group = None;
                     for ent in ents {
+                        if self.sort_by.groups() {
+                            let group = self.sort_by.group(ent);
+                            if last_group.as_ref() != Some(&group) {
+                                ui.label(
+                                    RichText::new(group.as_deref().unwrap_or("Unknown")).strong(),
+                                );
+                                last_group = Some(group);
+                            }
+                        }
                         let cached_promise = self.log_cache.entry(ent.id).or_insert_with(|| {
                             let (sender, promise) = Promise::new();
-                            crate::requests::get_log(&self.base_url, ent.id, sender, ctx.clone());
+                            crate::requests::get_log(
+                                &self.base_url,
+                                ent.id,
+                                &self.token,
+                                String::new(),
+                                sender,
+                                ctx.clone(),
+                            );
                             promise
                         });
 
@@ -203,12 +1075,61 @@ impl eframe::App for WRedNetDbgApp {
                                         .sense(Sense::click()),
                                     )
                                     .context_menu(|ui| {
-                                        if ui.button("\u{1F5D0} Copy IP").clicked() {
-                                            ui.output().copied_text = ent.addr.to_string();
+                                        if let Submitter::Addr(addr) = ent.addr {
+                                            if ui.button("\u{1F5D0} Copy IP").clicked() {
+                                                ui.output().copied_text = addr.to_string();
+                                                ui.close_menu();
+                                            }
+                                        }
+                                    });
+
+                                    let code = wred_server::session_code(ent.id);
+                                    ui.add(
+                                        Label::new(RichText::new(&code).monospace())
+                                            .sense(Sense::click()),
+                                    )
+                                    .context_menu(|ui| {
+                                        if ui.button("\u{1F5D0} Copy code").clicked() {
+                                            ui.output().copied_text = code.clone();
                                             ui.close_menu();
                                         }
                                     });
 
+                                    let status_color = match ent.status {
+                                        Status::New => Color32::LIGHT_BLUE,
+                                        Status::Triaging => Color32::YELLOW,
+                                        Status::NeedsInfo => Color32::LIGHT_RED,
+                                        Status::Resolved => Color32::LIGHT_GREEN,
+                                        Status::DuplicateOf(_) => Color32::GRAY,
+                                    };
+                                    ui.colored_label(status_color, ent.status.to_string());
+                                    if let Some(assignee) = &ent.assignee {
+                                        ui.label(RichText::new(format!("@{assignee}")).weak());
+                                    }
+
+                                    if !ent.meta.title.is_empty() {
+                                        ui.label(RichText::new(&ent.meta.title).strong());
+                                    }
+                                    for tag in &ent.meta.tags {
+                                        ui.label(RichText::new(format!("#{tag}")).small());
+                                    }
+                                    show_facts(ui, ent);
+                                    show_baseline_badge(ui, ent);
+                                    self.known_issues.show_badges(
+                                        ui,
+                                        ent,
+                                        &self.base_url,
+                                        &self.token,
+                                        ctx,
+                                    );
+                                    if let Some(label) = show_flag_badges(ui, ent) {
+                                        self.filter = if label.contains(' ') {
+                                            format!("flag:\"{label}\"")
+                                        } else {
+                                            format!("flag:{label}")
+                                        };
+                                    }
+
                                     let props = wred_server::get_id_props();
                                     let cur_micros = crate::utils::cur_micros();
                                     let micros = sequence_generator::decode_id_unix_epoch_micros(
@@ -249,7 +1170,7 @@ impl eframe::App for WRedNetDbgApp {
                                         }
 
                                         let resp = ui.add_enabled(
-                                            !self.secret.is_empty(),
+                                            !self.token.is_empty(),
                                             Button::new("\u{274C}"),
                                         );
                                         let id = resp.id.with("discard_confirmation");
@@ -259,12 +1180,16 @@ impl eframe::App for WRedNetDbgApp {
                                             ui.horizontal(|ui| {
                                                 if ui.button("Yes").clicked() {
                                                     ui.memory().close_popup();
+                                                    let (sender, promise) = Promise::new();
                                                     crate::requests::delete_log(
                                                         &self.base_url,
                                                         ent.id,
-                                                        &self.secret,
+                                                        &self.token,
+                                                        sender,
                                                         ctx.clone(),
                                                     );
+                                                    self.actions
+                                                        .push(("Deleting".to_owned(), promise));
                                                 }
                                                 if ui.button("No").clicked() {
                                                     ui.memory().close_popup();
@@ -276,7 +1201,7 @@ impl eframe::App for WRedNetDbgApp {
                                         }
 
                                         let resp = ui.add_enabled(
-                                            !self.secret.is_empty(),
+                                            !self.token.is_empty(),
                                             Button::new("\u{2705}"),
                                         );
                                         let id = resp.id.with("keep_confirmation");
@@ -286,12 +1211,16 @@ impl eframe::App for WRedNetDbgApp {
                                             ui.horizontal(|ui| {
                                                 if ui.button("Yes").clicked() {
                                                     ui.memory().close_popup();
+                                                    let (sender, promise) = Promise::new();
                                                     crate::requests::save_log(
                                                         &self.base_url,
                                                         ent.id,
-                                                        &self.secret,
+                                                        &self.token,
+                                                        sender,
                                                         ctx.clone(),
                                                     );
+                                                    self.actions
+                                                        .push(("Saving".to_owned(), promise));
                                                 }
                                                 if ui.button("No").clicked() {
                                                     ui.memory().close_popup();
@@ -302,44 +1231,279 @@ impl eframe::App for WRedNetDbgApp {
                                             if ent.is_saved {
                                                 ui.memory().open_popup(id);
                                             } else {
+                                                let (sender, promise) = Promise::new();
                                                 crate::requests::save_log(
                                                     &self.base_url,
                                                     ent.id,
-                                                    &self.secret,
+                                                    &self.token,
+                                                    sender,
                                                     ctx.clone(),
                                                 );
+                                                self.actions.push(("Saving".to_owned(), promise));
                                             }
                                         }
 
+                                        if can_archive
+                                            && ent.is_saved
+                                            && ui
+                                                .button("Unsave")
+                                                .on_hover_text(
+                                                    "Keep in memory only, until the server \
+                                                     restarts",
+                                                )
+                                                .clicked()
+                                        {
+                                            let (sender, promise) = Promise::new();
+                                            crate::requests::unsave_log(
+                                                &self.base_url,
+                                                ent.id,
+                                                &self.token,
+                                                sender,
+                                                ctx.clone(),
+                                            );
+                                            self.actions.push(("Unsaving".to_owned(), promise));
+                                        }
+                                        let archive_label = if ent.archived {
+                                            "Unarchive"
+                                        } else {
+                                            "\u{1F5C4} Archive"
+                                        };
+                                        if ui
+                                            .add_enabled(can_archive, Button::new(archive_label))
+                                            .on_hover_text(
+                                                "Archived logs only show up when the filter has \
+                                                 `archived:yes`",
+                                            )
+                                            .clicked()
+                                        {
+                                            let (sender, promise) = Promise::new();
+                                            crate::requests::set_archived(
+                                                &self.base_url,
+                                                ent.id,
+                                                !ent.archived,
+                                                &self.token,
+                                                sender,
+                                                ctx.clone(),
+                                            );
+                                            let name = if ent.archived {
+                                                "Unarchiving"
+                                            } else {
+                                                "Archiving"
+                                            };
+                                            self.actions.push((name.to_owned(), promise));
+                                        }
+
+                                        if ui
+                                            .add_enabled(
+                                                can_baseline
+                                                    && ent.is_saved
+                                                    && self.baseline_update.is_none(),
+                                                egui::SelectableLabel::new(
+                                                    ent.is_baseline,
+                                                    "\u{1F4CF}",
+                                                ),
+                                            )
+                                            .on_hover_text("Known-good log for this GPU and OS")
+                                            .on_disabled_hover_text(
+                                                "Only saved logs can be baselines",
+                                            )
+                                            .clicked()
+                                        {
+                                            let (sender, promise) = Promise::new();
+                                            crate::requests::set_baseline(
+                                                &self.base_url,
+                                                ent.id,
+                                                !ent.is_baseline,
+                                                &self.token,
+                                                sender,
+                                                ctx.clone(),
+                                            );
+                                            self.baseline_update = Some(promise);
+                                        }
+
+                                        if can_diff {
+                                            self.diff.show_button(ui, ent.id);
+                                        }
+                                        if can_sign {
+                                            self.known_issues.show_button(
+                                                ui,
+                                                ent.id,
+                                                &self.base_url,
+                                                &self.token,
+                                                ctx,
+                                            );
+                                        }
+
+                                        ui.add_enabled_ui(can_download, |ui| {
+                                            ui.menu_button("\u{2B07}", |ui| {
+                                                for (format, label) in DOWNLOAD_FORMATS {
+                                                    if ui.button(label).clicked() {
+                                                        ui.output().open_url =
+                                                            Some(egui::output::OpenUrl {
+                                                                url: crate::requests::download_url(
+                                                                    &self.base_url,
+                                                                    ent.id,
+                                                                    format,
+                                                                ),
+                                                                new_tab: true,
+                                                            });
+                                                        ui.close_menu();
+                                                    }
+                                                }
+                                            })
+                                            .response
+                                            .on_hover_text("Download");
+                                        });
+
                                         if ui.button("\u{1F5B9} Open URL").clicked() {
                                             ui.output().open_url = Some(egui::output::OpenUrl {
                                                 url: format!("{}/{}", self.base_url, ent.id),
                                                 new_tab: true,
                                             });
                                         }
+
+                                        if ui
+                                            .add_enabled(
+                                                can_share && self.share_link.is_none(),
+                                                Button::new("\u{1F517} Share"),
+                                            )
+                                            .on_hover_text("Copy a link that expires")
+                                            .clicked()
+                                        {
+                                            let (sender, promise) = Promise::new();
+                                            crate::requests::share_log(
+                                                &self.base_url,
+                                                ent.id,
+                                                &self.token,
+                                                &wred_server::ShareRequest {
+                                                    expires_in: SHARE_EXPIRY.as_secs(),
+                                                    lines: None,
+                                                },
+                                                sender,
+                                                ctx.clone(),
+                                            );
+                                            self.share_link = Some(promise);
+                                        }
+
+                                        let resp =
+                                            ui.add_enabled(can_edit, Button::new("\u{270F}"));
+                                        let id = resp.id.with("meta_editor");
+                                        if resp.clicked() {
+                                            self.meta_drafts.insert(ent.id, MetaDraft::new(ent));
+                                            ui.memory().open_popup(id);
+                                        }
+                                        egui::popup::popup_below_widget(ui, id, &resp, |ui| {
+                                            let Some(draft) = self.meta_drafts.get_mut(&ent.id)
+                                            else {
+                                                return;
+                                            };
+                                            match draft.show(ui, ent.id, &ids, can_triage) {
+                                                None => {}
+                                                Some(false) => {
+                                                    self.meta_drafts.remove(&ent.id);
+                                                }
+                                                Some(true) => {
+                                                    let (meta, triage) = draft.submit(
+                                                        ent,
+                                                        &ids,
+                                                        can_triage,
+                                                        &self.base_url,
+                                                        &self.token,
+                                                        ctx,
+                                                    );
+                                                    self.meta_updates.push((ent.id, meta));
+                                                    self.triage_updates.extend(
+                                                        triage.into_iter().map(|v| (ent.id, v)),
+                                                    );
+                                                    self.meta_drafts.remove(&ent.id);
+                                                }
+                                            }
+                                        });
                                     });
                                 });
                             })
-                            .body(|ui| match cached_promise.ready() {
-                                None => {
-                                    ui.spinner();
+                            .body(|ui| {
+                                if !ent.meta.notes.is_empty() {
+                                    ui.label(RichText::new(&ent.meta.notes).italics());
                                 }
-                                Some(Err(e)) => {
-                                    ui.horizontal(|ui| {
-                                        ui.label(RichText::new("\u{1F5D9}"));
-                                        ui.label(e);
-                                    });
+                                if let Some(summary) = ent.regression {
+                                    let regression = &self
+                                        .regressions
+                                        .entry(ent.id)
+                                        .and_modify(|v| {
+                                            if v.0 != summary {
+                                                *v = fetch_regression(
+                                                    &self.base_url,
+                                                    ent.id,
+                                                    summary,
+                                                    &self.token,
+                                                    ctx,
+                                                );
+                                            }
+                                        })
+                                        .or_insert_with(|| {
+                                            fetch_regression(
+                                                &self.base_url,
+                                                ent.id,
+                                                summary,
+                                                &self.token,
+                                                ctx,
+                                            )
+                                        })
+                                        .1;
+                                    if let Some(Ok(regression)) = regression.ready() {
+                                        show_regression(ui, regression);
+                                    }
                                 }
-                                Some(Ok(ent)) => {
-                                    Frame::canvas(ui.style()).show(ui, |ui| {
-                                        ui.add(
-                                            TextEdit::multiline(&mut ent.trim())
-                                                .code_editor()
-                                                .desired_width(f32::INFINITY)
-                                                .desired_rows(1)
-                                                .hint_text("Nothing to see here"),
+                                self.known_issues.show_resolutions(
+                                    ui,
+                                    ent,
+                                    &self.base_url,
+                                    &self.token,
+                                    ctx,
+                                );
+                                match cached_promise.ready() {
+                                    None => {
+                                        ui.spinner();
+                                    }
+                                    Some(Err(e)) => {
+                                        ui.horizontal(|ui| {
+                                            ui.label(RichText::new("\u{1F5D9}"));
+                                            ui.label(e);
+                                        });
+                                    }
+                                    Some(Ok(text)) if self.lines.show(ui, ent.id, text) => {}
+                                    Some(Ok(text)) if show_comments => {
+                                        self.comments.show_log(
+                                            ui,
+                                            ent.id,
+                                            text.trim_end(),
+                                            &self.base_url,
+                                            &self.token,
+                                            ctx,
                                         );
-                                    });
+                                    }
+                                    Some(Ok(ent)) => {
+                                        Frame::canvas(ui.style()).show(ui, |ui| {
+                                            ui.add(
+                                                TextEdit::multiline(&mut ent.trim())
+                                                    .code_editor()
+                                                    .desired_width(f32::INFINITY)
+                                                    .desired_rows(1)
+                                                    .hint_text("Nothing to see here"),
+                                            );
+                                        });
+                                    }
+                                }
+                                // Keep the end of an open log in view as it grows.
+                                let len = cached_promise
+                                    .ready()
+                                    .and_then(|v| v.as_ref().ok())
+                                    .map_or(0, String::len);
+                                let grew =
+                                    self.shown_lens.insert(ent.id, len).is_some_and(|v| v < len);
+                                if self.follow && grew {
+                                    ui.scroll_to_cursor(Some(Align::BOTTOM));
                                 }
                             })
                             .0
@@ -0,0 +1,274 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use egui::{Button, Color32, Frame, Label, RichText, Sense, TextEdit};
+use poll_promise::Promise;
+use wred_server::{Comment, CommentBody, LineRange};
+
+use crate::utils::Fetch;
+
+/// Width of the gutter column holding thread markers.
+const MARKER_WIDTH: f32 = 32.0;
+
+/// A comment being written, or edited if `editing` is set.
+#[derive(Default)]
+struct Draft {
+    editing: Option<u64>,
+    lines: String,
+    body: String,
+}
+
+/// Comment threads of the logs being viewed, with a line gutter to open them from.
+pub struct Comments {
+    cache: HashMap<u64, Fetch<Vec<Comment>>>,
+    drafts: HashMap<u64, Draft>,
+    /// `(log, line)` of the threads opened from the gutter.
+    expanded: HashSet<(u64, u32)>,
+    /// Changes in flight. The log's comments are refetched once each lands.
+    pending: Vec<(u64, Fetch<()>)>,
+    /// Why the last change to each log's comments failed, if it did.
+    failed: HashMap<u64, String>,
+    formatter: timeago::Formatter,
+}
+
+impl Default for Comments {
+    fn default() -> Self {
+        Self {
+            cache: HashMap::default(),
+            drafts: HashMap::default(),
+            expanded: HashSet::default(),
+            pending: Vec::new(),
+            failed: HashMap::default(),
+            formatter: timeago::Formatter::with_language(timeago::English),
+        }
+    }
+}
+
+impl Comments {
+    /// Forgets fetched comments, so they're refetched as logs are shown.
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+
+    /// Forgets log `id`'s fetched comments, so they're refetched when it's next shown.
+    pub fn refetch(&mut self, id: u64) {
+        self.cache.remove(&id);
+    }
+
+    pub fn forget(&mut self, id: u64) {
+        self.cache.remove(&id);
+        self.drafts.remove(&id);
+        self.failed.remove(&id);
+        self.expanded.retain(|&(log, _)| log != id);
+    }
+
+    fn poll(&mut self) {
+        for (id, promise) in std::mem::take(&mut self.pending) {
+            match promise.try_take() {
+                Err(promise) => self.pending.push((id, promise)),
+                Ok(result) => {
+                    match result {
+                        Ok(()) => self.failed.remove(&id),
+                        Err(e) => self.failed.insert(id, e),
+                    };
+                    self.cache.remove(&id);
+                }
+            }
+        }
+    }
+
+    /// Shows log `id` with line numbers, and markers in the gutter for lines with comments.
+    pub fn show_log(
+        &mut self,
+        ui: &mut egui::Ui,
+        id: u64,
+        text: &str,
+        base_url: &str,
+        token: &str,
+        ctx: &egui::Context,
+    ) {
+        self.poll();
+        let comments = self
+            .cache
+            .entry(id)
+            .or_insert_with(|| {
+                let (sender, promise) = Promise::new();
+                crate::requests::get_comments(base_url, id, token, sender, ctx.clone());
+                promise
+            })
+            .ready()
+            .and_then(|v| v.as_ref().ok())
+            .cloned()
+            .unwrap_or_default();
+
+        let line_count = text.lines().count();
+        let mut threads = BTreeMap::<u32, Vec<&Comment>>::new();
+        let mut general = Vec::new();
+        for comment in &comments {
+            match comment.lines {
+                Some(lines) if lines.first as usize <= line_count => {
+                    threads.entry(lines.first).or_default().push(comment);
+                }
+                _ => general.push(comment),
+            }
+        }
+
+        Frame::canvas(ui.style()).show(ui, |ui| {
+            ui.set_width(ui.available_width());
+            if text.is_empty() {
+                ui.weak("Nothing to see here");
+            }
+            for (n, line) in (1..).zip(text.lines()) {
+                let thread = threads.get(&n);
+                ui.horizontal(|ui| {
+                    let number = ui
+                        .add(
+                            Label::new(RichText::new(format!("{n:>5}")).monospace().weak())
+                                .sense(Sense::click()),
+                        )
+                        .on_hover_text("Comment on this line");
+                    if number.clicked() {
+                        let draft = self.drafts.entry(id).or_default();
+                        draft.lines = LineRange { first: n, last: n }.to_string();
+                    }
+
+                    let height = ui.spacing().interact_size.y;
+                    if let Some(thread) = thread {
+                        let marker = Button::new(format!("\u{1F4AC}{}", thread.len())).small();
+                        if ui.add_sized([MARKER_WIDTH, height], marker).clicked()
+                            && !self.expanded.remove(&(id, n))
+                        {
+                            self.expanded.insert((id, n));
+                        }
+                    } else {
+                        ui.add_space(MARKER_WIDTH);
+                    }
+
+                    ui.label(RichText::new(line).monospace());
+                });
+                if let Some(thread) = thread.filter(|_| self.expanded.contains(&(id, n))) {
+                    ui.indent(("thread", id, n), |ui| {
+                        for comment in thread {
+                            self.show_comment(ui, id, comment, base_url, token, ctx);
+                        }
+                    });
+                }
+            }
+        });
+
+        for comment in general {
+            self.show_comment(ui, id, comment, base_url, token, ctx);
+        }
+        if let Some(e) = self.failed.get(&id) {
+            ui.colored_label(Color32::RED, format!("Changing the comments failed: {e}"));
+        }
+        if !token.is_empty() {
+            self.show_form(ui, id, base_url, token, ctx);
+        }
+    }
+
+    fn show_comment(
+        &mut self,
+        ui: &mut egui::Ui,
+        id: u64,
+        comment: &Comment,
+        base_url: &str,
+        token: &str,
+        ctx: &egui::Context,
+    ) {
+        Frame::group(ui.style()).show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(RichText::new(&comment.author).strong());
+                if let Some(lines) = comment.lines {
+                    ui.weak(format!("lines {lines}"));
+                }
+                let cur_micros = crate::utils::cur_micros();
+                ui.weak(self.formatter.convert(std::time::Duration::from_micros(
+                    cur_micros.saturating_sub(comment.created),
+                )));
+                if comment.edited.is_some() {
+                    ui.weak("(edited)");
+                }
+
+                if token.is_empty() {
+                    return;
+                }
+                if ui.small_button("\u{270F}").clicked() {
+                    self.drafts.insert(
+                        id,
+                        Draft {
+                            editing: Some(comment.id),
+                            lines: comment.lines.map(|v| v.to_string()).unwrap_or_default(),
+                            body: comment.body.clone(),
+                        },
+                    );
+                }
+                if ui.small_button("\u{1F5D1}").clicked() {
+                    let (sender, promise) = Promise::new();
+                    crate::requests::delete_comment(
+                        base_url,
+                        id,
+                        comment.id,
+                        token,
+                        sender,
+                        ctx.clone(),
+                    );
+                    self.pending.push((id, promise));
+                }
+            });
+            ui.label(&comment.body);
+        });
+    }
+
+    fn show_form(
+        &mut self,
+        ui: &mut egui::Ui,
+        id: u64,
+        base_url: &str,
+        token: &str,
+        ctx: &egui::Context,
+    ) {
+        let draft = self.drafts.entry(id).or_default();
+        let lines = Some(draft.lines.trim())
+            .filter(|v| !v.is_empty())
+            .map(str::parse::<LineRange>);
+        ui.horizontal(|ui| {
+            ui.add(
+                TextEdit::singleline(&mut draft.lines)
+                    .desired_width(60.0)
+                    .hint_text("Lines"),
+            );
+            ui.add(
+                TextEdit::multiline(&mut draft.body)
+                    .desired_rows(1)
+                    .hint_text("Comment"),
+            );
+
+            let label = if draft.editing.is_some() {
+                "Save"
+            } else {
+                "Post"
+            };
+            let valid = !matches!(lines, Some(Err(_))) && !draft.body.trim().is_empty();
+            if ui.add_enabled(valid, Button::new(label)).clicked() {
+                let (sender, promise) = Promise::new();
+                crate::requests::put_comment(
+                    base_url,
+                    id,
+                    draft.editing,
+                    token,
+                    &CommentBody {
+                        lines: lines.and_then(Result::ok),
+                        body: draft.body.trim().to_owned(),
+                    },
+                    sender,
+                    ctx.clone(),
+                );
+                self.pending.push((id, promise));
+                *draft = Draft::default();
+            }
+            if draft.editing.is_some() && ui.button("Cancel").clicked() {
+                *draft = Draft::default();
+            }
+        });
+    }
+}
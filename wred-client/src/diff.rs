@@ -0,0 +1,225 @@
+use egui::{Align, Color32, Grid, RichText, ScrollArea};
+use poll_promise::Promise;
+use wred_server::{DiffLine, DiffTag, LogDiff, NumberedLine};
+
+use crate::utils::Fetch;
+
+/// Unchanged lines kept around each change when only changes are shown.
+const CONTEXT: usize = 3;
+
+/// A row of the side-by-side view. Deleted and inserted lines in the same spot share a row.
+struct Row<'a> {
+    old: Option<&'a NumberedLine>,
+    new: Option<&'a NumberedLine>,
+    changed: bool,
+    /// Lines were left out before this one.
+    after_gap: bool,
+}
+
+/// Pairs up the deleted and inserted lines of a change.
+fn flush<'a>(
+    rows: &mut Vec<Row<'a>>,
+    deleted: &mut Vec<&'a NumberedLine>,
+    inserted: &mut Vec<&'a NumberedLine>,
+) {
+    let len = deleted.len().max(inserted.len());
+    let mut deleted = std::mem::take(deleted).into_iter();
+    let mut inserted = std::mem::take(inserted).into_iter();
+    for _ in 0..len {
+        rows.push(Row {
+            old: deleted.next(),
+            new: inserted.next(),
+            changed: true,
+            after_gap: false,
+        });
+    }
+}
+
+fn rows(lines: &[DiffLine]) -> Vec<Row<'_>> {
+    let mut rows = Vec::new();
+    let mut deleted = Vec::new();
+    let mut inserted = Vec::new();
+    let mut last_old = 0;
+    for line in lines {
+        match line.tag {
+            DiffTag::Delete => deleted.extend(line.old.as_ref()),
+            DiffTag::Insert => inserted.extend(line.new.as_ref()),
+            DiffTag::Equal => {
+                flush(&mut rows, &mut deleted, &mut inserted);
+                let number = line.old.as_ref().map_or(0, |v| v.number);
+                rows.push(Row {
+                    old: line.old.as_ref(),
+                    new: line.new.as_ref(),
+                    changed: false,
+                    after_gap: number > last_old + 1,
+                });
+            }
+        }
+        if let Some(v) = &line.old {
+            last_old = v.number;
+        }
+    }
+    flush(&mut rows, &mut deleted, &mut inserted);
+    rows
+}
+
+/// Picks two logs to compare and shows their diff side by side.
+#[derive(Default)]
+pub struct DiffView {
+    /// Log picked as the old side, waiting for a second one.
+    picked: Option<u64>,
+    /// `(old, new)` of the diff being shown.
+    shown: Option<(u64, u64)>,
+    normalize: bool,
+    changes_only: bool,
+    diff: Option<Fetch<LogDiff>>,
+    scroll_to_first: bool,
+}
+
+impl DiffView {
+    /// Button in a log's header that picks it for comparing, or compares it with the log
+    /// picked before.
+    pub fn show_button(&mut self, ui: &mut egui::Ui, id: u64) {
+        match self.picked {
+            None => {
+                if ui
+                    .button("\u{21C4}")
+                    .on_hover_text("Compare with another log")
+                    .clicked()
+                {
+                    self.picked = Some(id);
+                }
+            }
+            Some(picked) if picked == id => {
+                if ui
+                    .button("\u{21C4} Cancel")
+                    .on_hover_text("Pick another log to compare this one with")
+                    .clicked()
+                {
+                    self.picked = None;
+                }
+            }
+            Some(picked) => {
+                if ui
+                    .button("\u{21C4} Compare")
+                    .on_hover_text(format!(
+                        "Compare {} with this log",
+                        wred_server::session_code(picked)
+                    ))
+                    .clicked()
+                {
+                    self.picked = None;
+                    self.shown = Some((picked, id));
+                    self.diff = None;
+                }
+            }
+        }
+    }
+
+    pub fn show_window(&mut self, ctx: &egui::Context, base_url: &str, token: &str) {
+        let Some(ids) = self.shown else {
+            return;
+        };
+        let diff = self.diff.take().unwrap_or_else(|| {
+            let (sender, promise) = Promise::new();
+            let context = self.changes_only.then_some(CONTEXT);
+            crate::requests::get_diff(
+                base_url,
+                ids,
+                self.normalize,
+                context,
+                token,
+                sender,
+                ctx.clone(),
+            );
+            self.scroll_to_first = true;
+            promise
+        });
+
+        let mut open = true;
+        let mut refetch = false;
+        let title = format!(
+            "{} \u{2192} {}",
+            wred_server::session_code(ids.0),
+            wred_server::session_code(ids.1)
+        );
+        egui::Window::new(title)
+            .id(egui::Id::new("diff"))
+            .open(&mut open)
+            .default_size([900.0, 600.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    refetch |= ui
+                        .checkbox(&mut self.normalize, "Normalize")
+                        .on_hover_text("Ignore timestamps, pointers and other volatile tokens")
+                        .changed();
+                    refetch |= ui
+                        .checkbox(&mut self.changes_only, "Changes only")
+                        .changed();
+                    if ui.button("First difference").clicked() {
+                        self.scroll_to_first = true;
+                    }
+                });
+                ui.separator();
+
+                match diff.ready() {
+                    None => {
+                        ui.spinner();
+                    }
+                    Some(Err(e)) => {
+                        ui.colored_label(Color32::RED, e);
+                    }
+                    Some(Ok(diff)) if diff.first_change.is_none() => {
+                        ui.weak("No differences");
+                    }
+                    Some(Ok(diff)) => {
+                        ScrollArea::both().show(ui, |ui| {
+                            self.show_rows(ui, diff);
+                        });
+                    }
+                }
+            });
+        if !open {
+            self.shown = None;
+        }
+        if open && !refetch {
+            self.diff = Some(diff);
+        }
+    }
+
+    fn show_rows(&mut self, ui: &mut egui::Ui, diff: &LogDiff) {
+        let side = |ui: &mut egui::Ui, line: Option<&NumberedLine>, color: Color32| {
+            if let Some(line) = line {
+                ui.label(RichText::new(line.number.to_string()).monospace().weak());
+                ui.label(RichText::new(&line.text).monospace().color(color));
+            } else {
+                ui.label("");
+                ui.label("");
+            }
+        };
+        Grid::new("diff_rows").num_columns(4).show(ui, |ui| {
+            let mut first = true;
+            for row in rows(&diff.lines) {
+                if row.after_gap {
+                    ui.weak("\u{22EF}");
+                    ui.end_row();
+                }
+                let (old_color, new_color) = if row.changed {
+                    (Color32::LIGHT_RED, Color32::LIGHT_GREEN)
+                } else {
+                    (Color32::GRAY, Color32::GRAY)
+                };
+                side(ui, row.old, old_color);
+                side(ui, row.new, new_color);
+                ui.end_row();
+                if row.changed && first {
+                    first = false;
+                    if self.scroll_to_first {
+                        self.scroll_to_first = false;
+                        ui.scroll_to_cursor(Some(Align::Center));
+                    }
+                }
+            }
+        });
+    }
+}
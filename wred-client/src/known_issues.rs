@@ -0,0 +1,247 @@
+use egui::{Color32, Frame, RichText, TextEdit};
+use poll_promise::Promise;
+use wred_server::{LogEntryPartial, NewSignature, Signature};
+
+use crate::utils::Fetch;
+
+const BADGE_COLOR: Color32 = Color32::LIGHT_BLUE;
+
+/// A signature being written, as typed.
+struct Editor {
+    /// Log it was drafted from.
+    from: u64,
+    title: String,
+    resolution: String,
+    /// One pattern per line.
+    patterns: String,
+    conditions: String,
+}
+
+impl Editor {
+    fn new(from: u64, draft: NewSignature) -> Self {
+        Self {
+            from,
+            title: draft.title,
+            resolution: draft.resolution,
+            patterns: draft.patterns.join("\n"),
+            conditions: draft.conditions,
+        }
+    }
+
+    fn to_signature(&self) -> NewSignature {
+        NewSignature {
+            title: self.title.trim().to_owned(),
+            resolution: self.resolution.trim().to_owned(),
+            patterns: self
+                .patterns
+                .lines()
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(ToOwned::to_owned)
+                .collect(),
+            conditions: self.conditions.trim().to_owned(),
+            drafted_from: Some(self.from),
+        }
+    }
+}
+
+/// Known issues logs match, and the window admins add signatures in.
+#[derive(Default)]
+pub struct KnownIssues {
+    signatures: Option<Fetch<Vec<Signature>>>,
+    /// Draft being fetched for a log.
+    draft: Option<(u64, Fetch<NewSignature>)>,
+    editor: Option<Editor>,
+    adding: Option<Fetch<Signature>>,
+    error: Option<String>,
+}
+
+impl KnownIssues {
+    /// Forgets fetched signatures, so they're refetched as logs are shown.
+    pub fn clear(&mut self) {
+        self.signatures = None;
+    }
+
+    fn signatures(&mut self, base_url: &str, token: &str, ctx: &egui::Context) -> &[Signature] {
+        self.signatures
+            .get_or_insert_with(|| {
+                let (sender, promise) = Promise::new();
+                crate::requests::get_signatures(base_url, token, sender, ctx.clone());
+                promise
+            })
+            .ready()
+            .and_then(|v| v.as_ref().ok())
+            .map_or(&[], Vec::as_slice)
+    }
+
+    fn matched<'a>(
+        &'a mut self,
+        ent: &'a LogEntryPartial,
+        base_url: &str,
+        token: &str,
+        ctx: &egui::Context,
+    ) -> impl Iterator<Item = &'a Signature> + 'a {
+        self.signatures(base_url, token, ctx)
+            .iter()
+            .filter(|v| ent.known_issues.contains(&v.id))
+    }
+
+    /// Badges for the known issues a log matches, with their resolution on hover.
+    pub fn show_badges(
+        &mut self,
+        ui: &mut egui::Ui,
+        ent: &LogEntryPartial,
+        base_url: &str,
+        token: &str,
+        ctx: &egui::Context,
+    ) {
+        if ent.known_issues.is_empty() {
+            return;
+        }
+        for signature in self.matched(ent, base_url, token, ctx) {
+            ui.colored_label(BADGE_COLOR, format!("\u{1F50E} {}", signature.title))
+                .on_hover_text(&signature.resolution);
+        }
+    }
+
+    /// The resolutions of the known issues a log matches.
+    pub fn show_resolutions(
+        &mut self,
+        ui: &mut egui::Ui,
+        ent: &LogEntryPartial,
+        base_url: &str,
+        token: &str,
+        ctx: &egui::Context,
+    ) {
+        if ent.known_issues.is_empty() {
+            return;
+        }
+        for signature in self.matched(ent, base_url, token, ctx) {
+            Frame::group(ui.style()).show(ui, |ui| {
+                ui.label(
+                    RichText::new(format!("Matches known issue {:?}", signature.title))
+                        .strong()
+                        .color(BADGE_COLOR),
+                );
+                if !signature.resolution.is_empty() {
+                    ui.label(&signature.resolution);
+                }
+            });
+        }
+    }
+
+    /// Button in a log's header that starts a signature from it.
+    pub fn show_button(
+        &mut self,
+        ui: &mut egui::Ui,
+        id: u64,
+        base_url: &str,
+        token: &str,
+        ctx: &egui::Context,
+    ) {
+        let busy = self.draft.is_some() || self.editor.is_some();
+        if ui
+            .add_enabled(!busy, egui::Button::new("\u{1F50E}+"))
+            .on_hover_text("Add a known issue matching logs like this one")
+            .clicked()
+        {
+            let (sender, promise) = Promise::new();
+            crate::requests::draft_signature(base_url, id, token, sender, ctx.clone());
+            self.draft = Some((id, promise));
+            self.error = None;
+        }
+    }
+
+    fn poll(&mut self) {
+        if let Some((from, promise)) = self.draft.take() {
+            match promise.try_take() {
+                Err(promise) => self.draft = Some((from, promise)),
+                Ok(Ok(draft)) => self.editor = Some(Editor::new(from, draft)),
+                Ok(Err(e)) => self.error = Some(e),
+            }
+        }
+        if let Some(promise) = self.adding.take() {
+            match promise.try_take() {
+                Err(promise) => self.adding = Some(promise),
+                Ok(Ok(_)) => {
+                    self.editor = None;
+                    self.signatures = None;
+                }
+                Ok(Err(e)) => self.error = Some(e),
+            }
+        }
+    }
+
+    pub fn show_window(&mut self, ctx: &egui::Context, base_url: &str, token: &str) {
+        self.poll();
+        let Some(editor) = &mut self.editor else {
+            if let Some(e) = &self.error {
+                let mut open = true;
+                egui::Window::new("Known issue")
+                    .open(&mut open)
+                    .show(ctx, |ui| ui.colored_label(Color32::RED, e));
+                if !open {
+                    self.error = None;
+                }
+            }
+            return;
+        };
+
+        let mut open = true;
+        let mut add = false;
+        egui::Window::new(format!(
+            "Known issue from {}",
+            wred_server::session_code(editor.from)
+        ))
+        .id(egui::Id::new("known_issue"))
+        .open(&mut open)
+        .show(ctx, |ui| {
+            egui::Grid::new("known_issue_fields")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Title");
+                    ui.text_edit_singleline(&mut editor.title);
+                    ui.end_row();
+                    ui.label("Resolution");
+                    ui.add(TextEdit::multiline(&mut editor.resolution).desired_rows(3));
+                    ui.end_row();
+                    ui.label("Patterns")
+                        .on_hover_text("Regexes, one per line, that all have to match");
+                    ui.add(
+                        TextEdit::multiline(&mut editor.patterns)
+                            .code_editor()
+                            .desired_rows(3),
+                    );
+                    ui.end_row();
+                    ui.label("Conditions")
+                        .on_hover_text("Filter the log's summary has to match");
+                    ui.add(
+                        TextEdit::singleline(&mut editor.conditions).hint_text("e.g. tag:ventura"),
+                    );
+                    ui.end_row();
+                });
+            if let Some(e) = &self.error {
+                ui.colored_label(Color32::RED, e);
+            }
+            add = ui
+                .add_enabled(self.adding.is_none(), egui::Button::new("Add"))
+                .clicked();
+        });
+        if add {
+            let (sender, promise) = Promise::new();
+            crate::requests::add_signature(
+                base_url,
+                &editor.to_signature(),
+                token,
+                sender,
+                ctx.clone(),
+            );
+            self.adding = Some(promise);
+            self.error = None;
+        }
+        if !open {
+            self.editor = None;
+            self.error = None;
+        }
+    }
+}
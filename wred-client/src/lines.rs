@@ -0,0 +1,115 @@
+use std::collections::{BTreeMap, HashMap};
+
+use egui::{Color32, Frame, RichText};
+use wred_server::{
+    parse::{ParsedLine, ParsedLog},
+    Severity,
+};
+
+/// A log's parsed lines, and how many lines each subsystem has among them.
+#[derive(Default)]
+struct Parsed {
+    log: ParsedLog,
+    subsystems: BTreeMap<String, usize>,
+    /// Lines counted into `subsystems` so far.
+    counted: usize,
+}
+
+impl Parsed {
+    fn update(&mut self, text: &str) {
+        self.log.update(text);
+        if self.log.lines().len() < self.counted {
+            self.subsystems.clear();
+            self.counted = 0;
+        }
+        for line in &self.log.lines()[self.counted..] {
+            if let Some(subsystem) = &line.subsystem {
+                *self.subsystems.entry(subsystem.clone()).or_default() += 1;
+            }
+        }
+        self.counted = self.log.lines().len();
+    }
+}
+
+/// Logs split into lines with the same parser as the server, for showing one subsystem's
+/// lines at a time.
+#[derive(Default)]
+pub struct Lines {
+    parsed: HashMap<u64, Parsed>,
+    /// Subsystem each log is filtered to.
+    filters: HashMap<u64, String>,
+}
+
+fn show_line(ui: &mut egui::Ui, line: &ParsedLine) {
+    let color = match line.severity {
+        Some(Severity::Error) => Color32::LIGHT_RED,
+        Some(Severity::Warning) => Color32::YELLOW,
+        None => ui.visuals().text_color(),
+    };
+    ui.horizontal(|ui| {
+        ui.label(
+            RichText::new(format!("{:>5}", line.number))
+                .monospace()
+                .weak(),
+        );
+        if let Some(function) = &line.function {
+            ui.label(
+                RichText::new(function)
+                    .monospace()
+                    .color(Color32::LIGHT_BLUE),
+            );
+        }
+        ui.label(RichText::new(&line.message).monospace().color(color));
+    });
+}
+
+impl Lines {
+    pub fn forget(&mut self, id: u64) {
+        self.parsed.remove(&id);
+        self.filters.remove(&id);
+    }
+
+    /// Shows a picker for the subsystem to filter log `id` to, if it has any, and the lines
+    /// of that subsystem. Returns `false` if the log isn't filtered, so it's up to the caller
+    /// to show it.
+    pub fn show(&mut self, ui: &mut egui::Ui, id: u64, text: &str) -> bool {
+        let parsed = self.parsed.entry(id).or_default();
+        parsed.update(text);
+        if parsed.subsystems.is_empty() {
+            return false;
+        }
+
+        let mut filter = self.filters.get(&id).cloned();
+        ui.horizontal(|ui| {
+            ui.label("Subsystem");
+            egui::ComboBox::from_id_source(("subsystem", id))
+                .selected_text(filter.as_deref().unwrap_or("All"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut filter, None, "All");
+                    for (subsystem, count) in &parsed.subsystems {
+                        ui.selectable_value(
+                            &mut filter,
+                            Some(subsystem.clone()),
+                            format!("{subsystem} ({count})"),
+                        );
+                    }
+                });
+        });
+        let Some(filter) = filter else {
+            self.filters.remove(&id);
+            return false;
+        };
+
+        let rest = parsed.log.rest(text);
+        Frame::canvas(ui.style()).show(ui, |ui| {
+            ui.set_width(ui.available_width());
+            for line in parsed.log.lines().iter().chain(&rest) {
+                if line.subsystem.as_ref() == Some(&filter) {
+                    show_line(ui, line);
+                }
+            }
+        });
+        self.filters.insert(id, filter);
+        true
+    }
+}
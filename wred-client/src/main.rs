@@ -11,7 +11,12 @@
 extern crate cfg_if;
 
 mod app;
+mod comments;
+mod diff;
+mod known_issues;
+mod lines;
 mod requests;
+mod stream;
 mod style;
 mod utils;
 
@@ -1,64 +1,474 @@
 use egui::Context;
+use serde::{de::DeserializeOwned, Serialize};
 
-pub fn delete_log(base_url: &str, id: u64, secret: &str, ctx: Context) {
-    ehttp::fetch(
+fn api_url(base_url: &str) -> String {
+    format!("{base_url}/api/v{}", wred_server::API_VERSION)
+}
+
+/// Describes a response that isn't a success.
+fn error(response: &ehttp::Response) -> String {
+    // The server explains client errors in the body.
+    let status = format!("{} {}", response.status, response.status_text);
+    match response.text().filter(|v| !v.is_empty()) {
+        Some(text) => format!("{status}: {text}"),
+        None => status,
+    }
+}
+
+/// Fetches and decodes a postcard body.
+fn get<T: DeserializeOwned + Send + 'static>(
+    request: ehttp::Request,
+    sender: poll_promise::Sender<Result<T, String>>,
+    ctx: Context,
+) {
+    ehttp::fetch(request, move |response| {
+        let ent = response.and_then(|v| {
+            if v.ok {
+                postcard::from_bytes(&v.bytes).map_err(|e| e.to_string())
+            } else {
+                Err(error(&v))
+            }
+        });
+        sender.send(ent);
+        ctx.request_repaint();
+    });
+}
+
+/// Adds the bearer token, if there is one. Reads work without a token on most servers.
+fn authorized(mut request: ehttp::Request, token: &str) -> ehttp::Request {
+    if !token.is_empty() {
+        request
+            .headers
+            .insert("Authorization".to_owned(), format!("Bearer {token}"));
+    }
+    request
+}
+
+pub fn log_url(base_url: &str, id: u64) -> String {
+    format!("{}/{id}", api_url(base_url))
+}
+
+/// URL that downloads log `id` as a file in `format`, like `text` or `html`.
+pub fn download_url(base_url: &str, id: u64, format: &str) -> String {
+    format!("{}/download?format={format}", log_url(base_url, id))
+}
+
+pub fn stream_url(base_url: &str) -> String {
+    format!("{}/stream", api_url(base_url))
+}
+
+pub fn get_server_info(
+    base_url: &str,
+    sender: poll_promise::Sender<Result<wred_server::ServerInfo, String>>,
+    ctx: Context,
+) {
+    get(
+        ehttp::Request::get(format!("{}/version", api_url(base_url))),
+        sender,
+        ctx,
+    );
+}
+
+pub fn delete_log(
+    base_url: &str,
+    id: u64,
+    token: &str,
+    sender: poll_promise::Sender<Result<(), String>>,
+    ctx: Context,
+) {
+    let request = authorized(
         ehttp::Request {
             method: "DELETE".to_owned(),
-            ..ehttp::Request::post(
-                format!("{base_url}/{id}"),
-                postcard::to_allocvec(secret).unwrap(),
-            )
-        },
-        move |response| {
-            if let Err(e) = response {
-                eprintln!("Error: {e}");
-            }
-            ctx.request_repaint();
+            ..ehttp::Request::post(log_url(base_url, id), Vec::new())
         },
+        token,
     );
+    send(request, sender, ctx);
 }
 
-pub fn save_log(base_url: &str, id: u64, secret: &str, ctx: Context) {
-    let request = ehttp::Request::post(
-        format!("{base_url}/{id}"),
-        postcard::to_allocvec(secret).unwrap(),
+pub fn save_log(
+    base_url: &str,
+    id: u64,
+    token: &str,
+    sender: poll_promise::Sender<Result<(), String>>,
+    ctx: Context,
+) {
+    let request = authorized(
+        ehttp::Request::post(log_url(base_url, id), Vec::new()),
+        token,
     );
-    ehttp::fetch(request, move |response| {
-        if let Err(e) = response {
-            eprintln!("Error: {e}");
-        }
-        ctx.request_repaint();
-    });
+    send(request, sender, ctx);
+}
+
+pub fn unsave_log(
+    base_url: &str,
+    id: u64,
+    token: &str,
+    sender: poll_promise::Sender<Result<(), String>>,
+    ctx: Context,
+) {
+    let request = authorized(
+        ehttp::Request::post(format!("{}/unsave", log_url(base_url, id)), Vec::new()),
+        token,
+    );
+    send(request, sender, ctx);
+}
+
+pub fn set_archived(
+    base_url: &str,
+    id: u64,
+    archived: bool,
+    token: &str,
+    sender: poll_promise::Sender<Result<(), String>>,
+    ctx: Context,
+) {
+    let action = if archived { "archive" } else { "unarchive" };
+    let request = authorized(
+        ehttp::Request::post(format!("{}/{action}", log_url(base_url, id)), Vec::new()),
+        token,
+    );
+    send(request, sender, ctx);
 }
 
 pub fn get_logs(
     base_url: &str,
+    token: &str,
     sender: poll_promise::Sender<Result<Vec<wred_server::LogEntryPartial>, String>>,
     ctx: Context,
 ) {
-    ehttp::fetch(
-        ehttp::Request::get(format!("{base_url}/all")),
-        move |response| {
-            let ent =
-                response.and_then(|v| postcard::from_bytes(&v.bytes).map_err(|e| e.to_string()));
-            sender.send(ent);
-            ctx.request_repaint();
-        },
+    get(
+        authorized(
+            ehttp::Request::get(format!("{}/all", api_url(base_url))),
+            token,
+        ),
+        sender,
+        ctx,
     );
 }
 
+/// Fetches a log, or only the part appended after `prefix` when one is already cached.
 pub fn get_log(
     base_url: &str,
     id: u64,
+    token: &str,
+    prefix: String,
     sender: poll_promise::Sender<Result<String, String>>,
     ctx: Context,
 ) {
     ehttp::fetch(
-        ehttp::Request::get(format!("{base_url}/{id}")),
+        authorized(
+            ehttp::Request::get(format!("{}?since={}", log_url(base_url, id), prefix.len())),
+            token,
+        ),
         move |response| {
-            let ent = response.map(|v| String::from_utf8_lossy(&v.bytes).into_owned());
+            let ent = response.and_then(|v| {
+                if v.ok {
+                    Ok(prefix + &String::from_utf8_lossy(&v.bytes))
+                } else {
+                    Err(format!("{} {}", v.status, v.status_text))
+                }
+            });
             sender.send(ent);
             ctx.request_repaint();
         },
     );
 }
+
+/// Mints a share link for a log. Posted as postcard, which the server assumes for non-JSON.
+pub fn share_log(
+    base_url: &str,
+    id: u64,
+    token: &str,
+    request: &wred_server::ShareRequest,
+    sender: poll_promise::Sender<Result<wred_server::ShareLink, String>>,
+    ctx: Context,
+) {
+    let body = postcard::to_allocvec(request).unwrap();
+    get(
+        authorized(
+            ehttp::Request::post(format!("{}/share", log_url(base_url, id)), body),
+            token,
+        ),
+        sender,
+        ctx,
+    );
+}
+
+/// Revokes every share link the server has handed out.
+pub fn rotate_share_links(
+    base_url: &str,
+    token: &str,
+    sender: poll_promise::Sender<Result<(), String>>,
+    ctx: Context,
+) {
+    let request = authorized(
+        ehttp::Request::post(format!("{}/share/rotate", api_url(base_url)), Vec::new()),
+        token,
+    );
+    send(request, sender, ctx);
+}
+
+pub fn patch_log(
+    base_url: &str,
+    id: u64,
+    token: &str,
+    patch: &wred_server::LogMetaPatch,
+    sender: poll_promise::Sender<Result<wred_server::LogMeta, String>>,
+    ctx: Context,
+) {
+    let body = postcard::to_allocvec(patch).unwrap();
+    get(
+        authorized(
+            ehttp::Request {
+                method: "PATCH".to_owned(),
+                ..ehttp::Request::post(log_url(base_url, id), body)
+            },
+            token,
+        ),
+        sender,
+        ctx,
+    );
+}
+
+fn put_triage<T: Serialize>(
+    url: String,
+    token: &str,
+    body: &T,
+    sender: poll_promise::Sender<Result<wred_server::HistoryEntry, String>>,
+    ctx: Context,
+) {
+    let body = postcard::to_allocvec(body).unwrap();
+    get(
+        authorized(
+            ehttp::Request {
+                method: "PUT".to_owned(),
+                ..ehttp::Request::post(url, body)
+            },
+            token,
+        ),
+        sender,
+        ctx,
+    );
+}
+
+pub fn set_status(
+    base_url: &str,
+    id: u64,
+    token: &str,
+    status: wred_server::Status,
+    sender: poll_promise::Sender<Result<wred_server::HistoryEntry, String>>,
+    ctx: Context,
+) {
+    let url = format!("{}/status", log_url(base_url, id));
+    put_triage(url, token, &status, sender, ctx);
+}
+
+pub fn set_assignee(
+    base_url: &str,
+    id: u64,
+    token: &str,
+    assignee: Option<String>,
+    sender: poll_promise::Sender<Result<wred_server::HistoryEntry, String>>,
+    ctx: Context,
+) {
+    let url = format!("{}/assignee", log_url(base_url, id));
+    put_triage(url, token, &assignee, sender, ctx);
+}
+
+/// Sends a request whose response body doesn't matter, only whether it succeeded.
+fn send(request: ehttp::Request, sender: poll_promise::Sender<Result<(), String>>, ctx: Context) {
+    ehttp::fetch(request, move |response| {
+        sender.send(response.and_then(|v| if v.ok { Ok(()) } else { Err(error(&v)) }));
+        ctx.request_repaint();
+    });
+}
+
+fn comments_url(base_url: &str, id: u64) -> String {
+    format!("{}/comments", log_url(base_url, id))
+}
+
+pub fn get_comments(
+    base_url: &str,
+    id: u64,
+    token: &str,
+    sender: poll_promise::Sender<Result<Vec<wred_server::Comment>, String>>,
+    ctx: Context,
+) {
+    get(
+        authorized(ehttp::Request::get(comments_url(base_url, id)), token),
+        sender,
+        ctx,
+    );
+}
+
+/// Posts a comment, or replaces comment `comment` if given.
+pub fn put_comment(
+    base_url: &str,
+    id: u64,
+    comment: Option<u64>,
+    token: &str,
+    body: &wred_server::CommentBody,
+    sender: poll_promise::Sender<Result<(), String>>,
+    ctx: Context,
+) {
+    let body = postcard::to_allocvec(body).unwrap();
+    let request = match comment {
+        None => ehttp::Request::post(comments_url(base_url, id), body),
+        Some(comment) => ehttp::Request {
+            method: "PUT".to_owned(),
+            ..ehttp::Request::post(format!("{}/{comment}", comments_url(base_url, id)), body)
+        },
+    };
+    send(authorized(request, token), sender, ctx);
+}
+
+pub fn delete_comment(
+    base_url: &str,
+    id: u64,
+    comment: u64,
+    token: &str,
+    sender: poll_promise::Sender<Result<(), String>>,
+    ctx: Context,
+) {
+    let request = ehttp::Request {
+        method: "DELETE".to_owned(),
+        ..ehttp::Request::post(
+            format!("{}/{comment}", comments_url(base_url, id)),
+            Vec::new(),
+        )
+    };
+    send(authorized(request, token), sender, ctx);
+}
+
+/// Runs a bulk operation, like `save` or `tag`, and fetches the outcome for each log.
+pub fn bulk<T: Serialize>(
+    base_url: &str,
+    op: &str,
+    token: &str,
+    body: &T,
+    sender: poll_promise::Sender<Result<Vec<wred_server::BulkResult>, String>>,
+    ctx: Context,
+) {
+    let body = postcard::to_allocvec(body).unwrap();
+    get(
+        authorized(
+            ehttp::Request::post(format!("{}/bulk/{op}", api_url(base_url)), body),
+            token,
+        ),
+        sender,
+        ctx,
+    );
+}
+
+/// Fetches the line diff from log `old` to log `new`. With `context`, unchanged lines further
+/// than that from a change are left out.
+pub fn get_diff(
+    base_url: &str,
+    (old, new): (u64, u64),
+    normalize: bool,
+    context: Option<usize>,
+    token: &str,
+    sender: poll_promise::Sender<Result<wred_server::LogDiff, String>>,
+    ctx: Context,
+) {
+    let mut url = format!(
+        "{}/diff/{old}/{new}?normalize={normalize}",
+        api_url(base_url)
+    );
+    if let Some(context) = context {
+        url += &format!("&context={context}");
+    }
+    get(authorized(ehttp::Request::get(url), token), sender, ctx);
+}
+
+/// Makes log `id` the baseline for its detected key, or stops it being one.
+pub fn set_baseline(
+    base_url: &str,
+    id: u64,
+    baseline: bool,
+    token: &str,
+    sender: poll_promise::Sender<Result<(), String>>,
+    ctx: Context,
+) {
+    let url = format!("{}/baseline", log_url(base_url, id));
+    let request = if baseline {
+        let body = postcard::to_allocvec(&None::<wred_server::BaselineKey>).unwrap();
+        ehttp::Request {
+            method: "PUT".to_owned(),
+            ..ehttp::Request::post(url, body)
+        }
+    } else {
+        ehttp::Request {
+            method: "DELETE".to_owned(),
+            ..ehttp::Request::post(url, Vec::new())
+        }
+    };
+    send(authorized(request, token), sender, ctx);
+}
+
+pub fn get_regression(
+    base_url: &str,
+    id: u64,
+    token: &str,
+    sender: poll_promise::Sender<Result<wred_server::Regression, String>>,
+    ctx: Context,
+) {
+    get(
+        authorized(
+            ehttp::Request::get(format!("{}/regression", log_url(base_url, id))),
+            token,
+        ),
+        sender,
+        ctx,
+    );
+}
+
+fn signatures_url(base_url: &str) -> String {
+    format!("{}/signatures", api_url(base_url))
+}
+
+pub fn get_signatures(
+    base_url: &str,
+    token: &str,
+    sender: poll_promise::Sender<Result<Vec<wred_server::Signature>, String>>,
+    ctx: Context,
+) {
+    get(
+        authorized(ehttp::Request::get(signatures_url(base_url)), token),
+        sender,
+        ctx,
+    );
+}
+
+pub fn add_signature(
+    base_url: &str,
+    signature: &wred_server::NewSignature,
+    token: &str,
+    sender: poll_promise::Sender<Result<wred_server::Signature, String>>,
+    ctx: Context,
+) {
+    let body = postcard::to_allocvec(signature).unwrap();
+    get(
+        authorized(ehttp::Request::post(signatures_url(base_url), body), token),
+        sender,
+        ctx,
+    );
+}
+
+/// Fetches a signature drafted from log `id`, to edit before adding it.
+pub fn draft_signature(
+    base_url: &str,
+    id: u64,
+    token: &str,
+    sender: poll_promise::Sender<Result<wred_server::NewSignature, String>>,
+    ctx: Context,
+) {
+    get(
+        authorized(
+            ehttp::Request::get(format!("{}/signature-draft", log_url(base_url, id))),
+            token,
+        ),
+        sender,
+        ctx,
+    );
+}
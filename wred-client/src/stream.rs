@@ -0,0 +1,149 @@
+use std::sync::mpsc::{channel, Receiver, TryIter};
+
+use egui::Context;
+use wred_server::LogEvent;
+
+/// A live tail subscription. Dropping it closes the connection.
+pub struct LogStream {
+    /// Events, or why the connection was lost, after which nothing more comes.
+    rx: Receiver<Result<LogEvent, String>>,
+    #[cfg(target_arch = "wasm32")]
+    source: web_sys::EventSource,
+    #[cfg(target_arch = "wasm32")]
+    _on_message: wasm_bindgen::closure::Closure<dyn FnMut(web_sys::MessageEvent)>,
+    #[cfg(target_arch = "wasm32")]
+    _on_open: wasm_bindgen::closure::Closure<dyn FnMut()>,
+    #[cfg(target_arch = "wasm32")]
+    _on_error: wasm_bindgen::closure::Closure<dyn FnMut()>,
+}
+
+impl LogStream {
+    /// Subscribes to the live tail. With `catch_up`, a [`LogEvent::Lagged`] comes first once
+    /// connected, so whatever was missed since an earlier subscription was lost is fetched.
+    pub fn open(base_url: &str, token: &str, catch_up: bool, ctx: Context) -> Self {
+        let url = crate::requests::stream_url(base_url);
+        let (tx, rx) = channel();
+
+        cfg_if! {
+            if #[cfg(target_arch = "wasm32")] {
+                use wasm_bindgen::JsCast;
+
+                // `EventSource` can't set headers, so the token goes in the query.
+                let url = if token.is_empty() {
+                    url
+                } else {
+                    format!(
+                        "{url}?access_token={}",
+                        js_sys::encode_uri_component(token)
+                    )
+                };
+                let source = web_sys::EventSource::new(&url).unwrap();
+                let on_message = {
+                    let (tx, ctx) = (tx.clone(), ctx.clone());
+                    wasm_bindgen::closure::Closure::<dyn FnMut(_)>::new(
+                        move |e: web_sys::MessageEvent| {
+                            if let Some(event) = e
+                                .data()
+                                .as_string()
+                                .and_then(|v| serde_json::from_str(&v).ok())
+                            {
+                                let _e = tx.send(Ok(event));
+                                ctx.request_repaint();
+                            }
+                        },
+                    )
+                };
+                // The browser reconnects by itself, but doesn't replay what was sent meanwhile.
+                let on_open = {
+                    let (tx, ctx) = (tx.clone(), ctx.clone());
+                    let mut catch_up = catch_up;
+                    wasm_bindgen::closure::Closure::<dyn FnMut()>::new(move || {
+                        if catch_up {
+                            let _e = tx.send(Ok(LogEvent::Lagged));
+                            ctx.request_repaint();
+                        }
+                        catch_up = true;
+                    })
+                };
+                // Only reported once the browser gives up, as it does on errors like a 401.
+                let on_error = {
+                    let source = source.clone();
+                    wasm_bindgen::closure::Closure::<dyn FnMut()>::new(move || {
+                        if source.ready_state() == web_sys::EventSource::CLOSED {
+                            let _e = tx.send(Err("Connection closed".to_owned()));
+                            ctx.request_repaint();
+                        }
+                    })
+                };
+                source.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+                source.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+                source.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+                Self {
+                    rx,
+                    source,
+                    _on_message: on_message,
+                    _on_open: on_open,
+                    _on_error: on_error,
+                }
+            } else {
+                use std::io::BufRead;
+
+                let mut request = ureq::get(&url);
+                if !token.is_empty() {
+                    request = request.set("Authorization", &format!("Bearer {token}"));
+                }
+                std::thread::spawn(move || {
+                    let resp = match request.call() {
+                        Ok(v) => v,
+                        Err(e) => {
+                            let _e = tx.send(Err(e.to_string()));
+                            ctx.request_repaint();
+                            return;
+                        }
+                    };
+                    if catch_up {
+                        if tx.send(Ok(LogEvent::Lagged)).is_err() {
+                            return;
+                        }
+                        ctx.request_repaint();
+                    }
+                    // Keep-alive comments make sure this notices a dropped receiver.
+                    for line in std::io::BufReader::new(resp.into_reader()).lines() {
+                        let line = match line {
+                            Ok(v) => v,
+                            Err(e) => {
+                                let _e = tx.send(Err(e.to_string()));
+                                ctx.request_repaint();
+                                return;
+                            }
+                        };
+                        let Some(event) = line
+                            .strip_prefix("data: ")
+                            .and_then(|v| serde_json::from_str(v).ok())
+                        else {
+                            continue;
+                        };
+                        if tx.send(Ok(event)).is_err() {
+                            return;
+                        }
+                        ctx.request_repaint();
+                    }
+                    let _e = tx.send(Err("Connection closed".to_owned()));
+                    ctx.request_repaint();
+                });
+                Self { rx }
+            }
+        }
+    }
+
+    pub fn events(&self) -> TryIter<'_, Result<LogEvent, String>> {
+        self.rx.try_iter()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Drop for LogStream {
+    fn drop(&mut self) {
+        self.source.close();
+    }
+}
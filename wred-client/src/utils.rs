@@ -1,3 +1,6 @@
+/// A request in flight, or its outcome.
+pub type Fetch<T> = poll_promise::Promise<Result<T, String>>;
+
 pub fn cur_micros() -> u64 {
     cfg_if! {
         if #[cfg(target_arch = "wasm32")] {
@@ -0,0 +1,208 @@
+//! Pseudonyms stored in place of submitter addresses, so raw IPs don't sit on disk with saved
+//! logs. Which address each pseudonym stands for is kept in `addr-map.ron` in the log directory
+//! for a limited time, for admins only.
+
+use std::{
+    collections::BTreeMap,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    path::PathBuf,
+};
+
+use serde::{Deserialize, Serialize};
+use wred_server::AddrMapping;
+
+use crate::state::AppState;
+
+/// How often mappings past their retention are forgotten.
+const PRUNE_INTERVAL: std::time::Duration = std::time::Duration::from_hours(1);
+
+/// What's stored as the address of new sessions.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AddrStorage {
+    #[default]
+    Full,
+    /// A keyed hash of the address, as a unique local IPv6 address.
+    Hashed,
+    /// The address's /24 network for IPv4, or /48 for IPv6.
+    Truncated,
+}
+
+/// The address to store for `ip`. Ports are dropped unless storing the full address.
+pub fn pseudonymize(storage: AddrStorage, key: &[u8], ip: IpAddr) -> IpAddr {
+    match (storage, ip) {
+        (AddrStorage::Full, ip) => ip,
+        (AddrStorage::Hashed, ip) => {
+            let octets = match ip {
+                IpAddr::V4(v) => v.octets().to_vec(),
+                IpAddr::V6(v) => v.octets().to_vec(),
+            };
+            let mut hash: [u8; 16] = crate::signing::sign(key, &[b"stored-addr", &octets])[..16]
+                .try_into()
+                .unwrap();
+            hash[0] = 0xfd;
+            IpAddr::V6(Ipv6Addr::from(hash))
+        }
+        (AddrStorage::Truncated, IpAddr::V4(v)) => {
+            let [a, b, c, _] = v.octets();
+            IpAddr::V4(Ipv4Addr::new(a, b, c, 0))
+        }
+        (AddrStorage::Truncated, IpAddr::V6(v)) => {
+            let mut octets = v.octets();
+            octets[6..].fill(0);
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+    }
+}
+
+/// Mappings by the address they're for.
+#[derive(Debug, Default)]
+pub struct AddrMap(BTreeMap<IpAddr, AddrMapping>);
+
+impl AppState {
+    fn addr_map_path(&self) -> PathBuf {
+        self.config.log_dir.join("addr-map.ron")
+    }
+
+    pub async fn load_addr_map(&self) -> std::io::Result<()> {
+        let s = match tokio::fs::read_to_string(self.addr_map_path()).await {
+            Ok(v) => v,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let list = ron::de::from_str::<Vec<AddrMapping>>(&s).map_err(std::io::Error::other)?;
+        self.addr_map.lock().unwrap().0 = list.into_iter().map(|v| (v.addr, v)).collect();
+        self.prune_addr_map();
+        self.write_addr_map().await
+    }
+
+    async fn write_addr_map(&self) -> std::io::Result<()> {
+        let list = self.addr_mappings(None);
+        let s = ron::ser::to_string_pretty(&list, ron::ser::PrettyConfig::default())
+            .map_err(std::io::Error::other)?;
+        tokio::fs::write(self.addr_map_path(), s).await
+    }
+
+    /// When mappings last seen before are forgotten.
+    pub fn addr_map_cutoff(&self) -> u64 {
+        let retention = self
+            .config
+            .addr_map_days
+            .saturating_mul(24 * 60 * 60 * 1_000_000);
+        crate::state::unix_micros().saturating_sub(retention)
+    }
+
+    /// Forgets mappings not seen for longer than they're kept. Returns whether any were.
+    fn prune_addr_map(&self) -> bool {
+        let cutoff = self.addr_map_cutoff();
+        let mut map = self.addr_map.lock().unwrap();
+        let len = map.0.len();
+        map.0.retain(|_, v| v.last_seen > cutoff);
+        len != map.0.len()
+    }
+
+    /// The address to store for a connection from `addr`, remembering which address it stands
+    /// for if it's a pseudonym. Fails if the mapping can't be saved.
+    pub async fn store_addr(&self, addr: SocketAddr) -> std::io::Result<SocketAddr> {
+        let storage = self.config.stored_addr;
+        if storage == AddrStorage::Full {
+            return Ok(addr);
+        }
+        let pseudonym = pseudonymize(storage, self.signing_key(), addr.ip());
+        if self.config.addr_map_days > 0 {
+            self.remember_addr(addr.ip(), pseudonym, crate::state::unix_micros());
+            // Written every time, so a restart doesn't forget mappings still in use sooner.
+            self.write_addr_map().await?;
+        }
+        // Connections always have a port, so this also tells pseudonyms from full addresses.
+        Ok(SocketAddr::new(pseudonym, 0))
+    }
+
+    /// Remembers that `ip` is stored as `pseudonym`, as of `seen` unless it was seen later.
+    fn remember_addr(&self, ip: IpAddr, pseudonym: IpAddr, seen: u64) {
+        let mut map = self.addr_map.lock().unwrap();
+        let mapping = map.0.entry(ip).or_insert(AddrMapping {
+            pseudonym,
+            addr: ip,
+            last_seen: seen,
+        });
+        mapping.pseudonym = pseudonym;
+        mapping.last_seen = mapping.last_seen.max(seen);
+        drop(map);
+    }
+
+    /// Stores pseudonyms in place of the full addresses of logs saved before the server was
+    /// set to, remembering which address each stands for as of when the log was last updated.
+    pub async fn migrate_addrs(&self) -> std::io::Result<()> {
+        let storage = self.config.stored_addr;
+        if storage == AddrStorage::Full {
+            return Ok(());
+        }
+        let ids = {
+            let mut logs = self.logs.lock().unwrap();
+            let ids = logs
+                .iter_mut()
+                .filter(|(_, v)| v.addr.port() != 0)
+                .map(|(&id, v)| {
+                    let pseudonym = pseudonymize(storage, self.signing_key(), v.addr.ip());
+                    if self.config.addr_map_days > 0 {
+                        self.remember_addr(v.addr.ip(), pseudonym, v.last_updated);
+                    }
+                    v.addr = SocketAddr::new(pseudonym, 0);
+                    id
+                })
+                .collect::<Vec<_>>();
+            drop(logs);
+            ids
+        };
+        if ids.is_empty() {
+            return Ok(());
+        }
+        for id in ids {
+            self.write_log(id).await?;
+        }
+        self.prune_addr_map();
+        self.write_addr_map().await
+    }
+
+    /// Forgets which pseudonym `ip` was stored as. Returns how many mappings were removed.
+    pub async fn forget_addr(&self, ip: IpAddr) -> std::io::Result<u32> {
+        let removed = self.addr_map.lock().unwrap().0.remove(&ip).is_some();
+        if removed {
+            self.write_addr_map().await?;
+        }
+        Ok(u32::from(removed))
+    }
+
+    /// Mappings still kept, only those for `pseudonym` if given.
+    pub fn addr_mappings(&self, pseudonym: Option<IpAddr>) -> Vec<AddrMapping> {
+        self.addr_map
+            .lock()
+            .unwrap()
+            .0
+            .values()
+            .filter(|v| pseudonym.is_none_or(|p| v.pseudonym == p))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Forgets mappings past their retention in the background. Saving is retried every round
+/// until it succeeds, so forgotten mappings don't stay on disk.
+pub fn start(state: actix_web::web::Data<AppState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+        let mut unsaved = false;
+        loop {
+            interval.tick().await;
+            if state.prune_addr_map() || unsaved {
+                unsaved = match state.write_addr_map().await {
+                    Ok(()) => false,
+                    Err(e) => {
+                        eprintln!("Failed saving pruned address map, retrying: {e}");
+                        true
+                    }
+                };
+            }
+        }
+    });
+}
@@ -0,0 +1,273 @@
+//! Extracts facts from sessions and matches them against baselines: known-good logs for the
+//! same GPU and OS. Sessions are analyzed in the background once their data stops changing for
+//! a moment, and matched against the known-issue signatures then too.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use actix_web::web;
+use regex::Regex;
+use tokio::sync::broadcast;
+use wred_server::{Analysis, BaselineKey, LogEntry, LogEvent, Regression, RegressionLine};
+
+use crate::state::AppState;
+
+/// How long a session's data has to stay unchanged before it's analyzed.
+const ANALYZE_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Error and warning lines of `data`, numbered from `first`, and normalized for comparing.
+fn flagged_lines<'a>(
+    data: &'a str,
+    first: u32,
+    normalizers: &'a [(Regex, String)],
+) -> impl Iterator<Item = (u32, wred_server::Severity, &'a str, String)> + 'a {
+    (first..)
+        .zip(data.lines())
+        .filter_map(move |(number, line)| {
+            let severity = wred_server::severity(line)?;
+            Some((
+                number,
+                severity,
+                line,
+                crate::diff::normalize(line, normalizers),
+            ))
+        })
+}
+
+/// The baseline log `id` is compared against, if it has one.
+fn find_baseline(logs: &HashMap<u64, LogEntry>, id: u64) -> Option<u64> {
+    let v = logs.get(&id)?;
+    let key = v.key.as_ref().filter(|_| !v.is_baseline)?;
+    logs.iter()
+        .find(|(_, v)| v.is_baseline && v.key.as_ref() == Some(key))
+        .map(|(&id, _)| id)
+}
+
+/// Extracts facts from the lines of log `id` added since it was last analyzed, detects its key
+/// from them if it isn't known yet, and compares them against the baseline with the same key.
+/// Everything is analyzed again if the baseline changed. Returns whether anything changed.
+pub fn analyze(state: &AppState, id: u64) -> bool {
+    // Only what's new is copied out, so the analysis doesn't hold up everything else waiting on
+    // the logs.
+    let (analysis, tail, baseline_data, mut changed) = {
+        let mut logs = state.logs.lock().unwrap();
+        let baseline = find_baseline(&logs, id);
+        let Some(v) = logs.get(&id) else {
+            return false;
+        };
+        let start_over = baseline != v.analysis.baseline || v.data.get(v.analysis.end..).is_none();
+        // Only copied when it becomes the baseline, as what it has is all that's needed of it.
+        let baseline_data = baseline
+            .filter(|_| start_over)
+            .map(|v| logs[&v].data.clone());
+        let v = logs.get_mut(&id).unwrap();
+        let mut changed = false;
+        if start_over {
+            changed = v.regression.is_some() || baseline.is_some();
+            v.analysis = Analysis {
+                baseline,
+                ..Analysis::default()
+            };
+            v.regression = baseline.map(|baseline| Regression {
+                baseline,
+                lines: Vec::new(),
+            });
+        }
+        let copy = (
+            v.analysis.clone(),
+            v.data[v.analysis.end..].to_owned(),
+            baseline_data,
+            changed,
+        );
+        drop(logs);
+        copy
+    };
+    let known = baseline_data.map_or(analysis.known, |v| {
+        Arc::new(
+            flagged_lines(&v, 1, &state.normalizers)
+                .map(|(.., normalized)| normalized)
+                .collect(),
+        )
+    });
+    let facts = state.extractors.extract(&tail);
+    let regressed = flagged_lines(&tail, analysis.lines + 1, &state.normalizers)
+        .filter(|(.., normalized)| !known.contains(normalized))
+        .map(|(number, severity, text, _)| RegressionLine {
+            number,
+            severity,
+            text: text.to_owned(),
+        })
+        .collect::<Vec<_>>();
+    let complete = tail.rfind('\n').map_or(0, |n| n + 1);
+    let lines =
+        analysis.lines + u32::try_from(tail[..complete].lines().count()).unwrap_or(u32::MAX);
+
+    let mut logs = state.logs.lock().unwrap();
+    let Some(v) = logs.get_mut(&id) else {
+        return false;
+    };
+    // Analyzed in the meantime, like when the baseline changed.
+    if v.analysis.end != analysis.end || v.analysis.baseline != analysis.baseline {
+        return false;
+    }
+    changed |= state.extractors.merge(&mut v.facts, facts);
+    let detected = if v.key.is_none() {
+        v.key = crate::facts::baseline_key(&v.facts);
+        v.key.is_some()
+    } else {
+        false
+    };
+    if let Some(regression) = &mut v.regression {
+        // The line after the complete ones was compared before, while it wasn't complete.
+        let n = regression
+            .lines
+            .partition_point(|v| v.number <= analysis.lines);
+        changed |= regression.lines[n..] != regressed;
+        regression.lines.truncate(n);
+        regression.lines.extend(regressed);
+    }
+    v.analysis = Analysis {
+        end: analysis.end + complete,
+        lines,
+        known,
+        ..analysis
+    };
+    changed |= detected;
+    if changed {
+        state.notify(LogEvent::Session(state.partial(id, v)));
+    }
+    drop(logs);
+    // A newly detected key may come with a baseline to compare against.
+    if detected {
+        analyze(state, id);
+    }
+    changed
+}
+
+/// Analyzes every log whose key is `key`, like when its baseline changes.
+pub fn analyze_key(state: &AppState, key: &BaselineKey) -> Vec<u64> {
+    let ids = {
+        let logs = state.logs.lock().unwrap();
+        let ids = logs
+            .iter()
+            .filter(|(_, v)| !v.is_baseline && v.key.as_ref() == Some(key))
+            .map(|(&id, _)| id)
+            .collect::<Vec<_>>();
+        drop(logs);
+        ids
+    };
+    ids.into_iter().filter(|&id| analyze(state, id)).collect()
+}
+
+/// Saves the analysis of logs that are saved, so it survives restarts.
+pub async fn persist(state: &AppState, ids: Vec<u64>) -> std::io::Result<()> {
+    for id in ids {
+        if state.is_saved(id) {
+            state.write_log(id).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Takes the sessions that haven't changed for [`ANALYZE_DELAY`] out of `dirty`.
+fn take_ready(dirty: &mut HashMap<u64, tokio::time::Instant>) -> Vec<u64> {
+    let now = tokio::time::Instant::now();
+    let ready = dirty
+        .iter()
+        .filter(|(_, &v)| v + ANALYZE_DELAY <= now)
+        .map(|(&id, _)| id)
+        .collect::<Vec<_>>();
+    for id in &ready {
+        dirty.remove(id);
+    }
+    ready
+}
+
+/// Analyzes sessions as data arrives, and matches them against signatures as they change.
+pub fn start(state: web::Data<AppState>) {
+    let mut rx = state.events.subscribe();
+    tokio::spawn(async move {
+        // Sessions with new data, and when they last changed.
+        // Logs saved before facts were extracted get them now.
+        let mut dirty = state
+            .logs
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, v)| v.facts.is_empty())
+            .map(|(&id, _)| (id, tokio::time::Instant::now()))
+            .collect::<HashMap<_, _>>();
+        // Sessions with new data or a new summary, to match against signatures.
+        let mut unmatched = HashMap::<u64, tokio::time::Instant>::new();
+        loop {
+            let next = dirty
+                .values()
+                .chain(unmatched.values())
+                .min()
+                .map(|&v| v + ANALYZE_DELAY);
+            let event = match next {
+                Some(next) => tokio::time::timeout_at(next, rx.recv()).await.ok(),
+                None => Some(rx.recv().await),
+            };
+            match event {
+                Some(Ok(LogEvent::Append { id, .. })) => {
+                    dirty.insert(id, tokio::time::Instant::now());
+                    unmatched.insert(id, tokio::time::Instant::now());
+                }
+                Some(Ok(LogEvent::Session(v))) => {
+                    // Matching only sends another event if the known issues change, so this
+                    // settles.
+                    unmatched.insert(v.id, tokio::time::Instant::now());
+                    if v.key.is_none() && !v.is_saved {
+                        dirty.entry(v.id).or_insert_with(tokio::time::Instant::now);
+                    }
+                }
+                Some(Ok(LogEvent::Removed(id))) => {
+                    dirty.remove(&id);
+                    unmatched.remove(&id);
+                }
+                Some(Err(broadcast::error::RecvError::Lagged(_))) => {
+                    // There's no telling which sessions the missed events were for.
+                    let now = tokio::time::Instant::now();
+                    let ids = state
+                        .logs
+                        .lock()
+                        .unwrap()
+                        .keys()
+                        .copied()
+                        .collect::<Vec<_>>();
+                    dirty.extend(ids.iter().map(|&id| (id, now)));
+                    unmatched.extend(ids.iter().map(|&id| (id, now)));
+                }
+                Some(Ok(_)) => {}
+                Some(Err(broadcast::error::RecvError::Closed)) => return,
+                None => {
+                    let ready = take_ready(&mut dirty);
+                    let ready_to_match = take_ready(&mut unmatched);
+                    let state = state.clone();
+                    let Ok((state, changed)) = web::block(move || {
+                        let mut changed = ready
+                            .into_iter()
+                            .filter(|&id| analyze(&state, id))
+                            .collect::<HashSet<_>>();
+                        changed.extend(
+                            ready_to_match
+                                .into_iter()
+                                .filter(|&id| state.match_signatures(id)),
+                        );
+                        (state, changed.into_iter().collect())
+                    })
+                    .await
+                    else {
+                        continue;
+                    };
+                    if let Err(e) = persist(&state, changed).await {
+                        eprintln!("Failed saving analysis: {e}");
+                    }
+                }
+            }
+        }
+    });
+}
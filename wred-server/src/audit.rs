@@ -0,0 +1,242 @@
+//! Append-only record of every API call that could change something: who made it, from where,
+//! what it was and how it went. Kept in `audit.jsonl` in the log directory, one JSON object per
+//! line, and rotated to `audit.jsonl.1`, `.2` and so on as it grows past `audit_max_bytes`.
+
+use std::{
+    collections::HashSet,
+    future::{ready, Ready},
+    net::IpAddr,
+    path::PathBuf,
+};
+
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::Method,
+    web, Error, HttpMessage,
+};
+use futures_util::future::LocalBoxFuture;
+use tokio::io::AsyncWriteExt;
+use wred_server::AuditEntry;
+
+use crate::{
+    addrs::{pseudonymize, AddrStorage},
+    state::AppState,
+};
+
+/// Serializes writing and rotating the audit log.
+#[derive(Debug, Default)]
+pub struct AuditLog(tokio::sync::Mutex<()>);
+
+const fn is_mutating(method: &Method) -> bool {
+    !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+/// The first all-digit segment of `path`, which is how routes name the log or other thing they
+/// act on.
+fn target(path: &str) -> Option<u64> {
+    path.split('/')
+        .find(|v| !v.is_empty() && v.bytes().all(|v| v.is_ascii_digit()))?
+        .parse()
+        .ok()
+}
+
+impl AppState {
+    /// The current audit log for `0`, otherwise the `n`th most recently rotated one.
+    fn audit_path(&self, n: usize) -> PathBuf {
+        let path = self.config.log_dir.join("audit.jsonl");
+        if n == 0 {
+            path
+        } else {
+            path.with_extension(format!("jsonl.{n}"))
+        }
+    }
+
+    async fn rotate_audit(&self) -> std::io::Result<()> {
+        let keep = self.config.audit_keep;
+        if keep == 0 {
+            return tokio::fs::remove_file(self.audit_path(0)).await;
+        }
+        for n in (0..keep).rev() {
+            match tokio::fs::rename(self.audit_path(n), self.audit_path(n + 1)).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes sure the audit log can be written, so a server that couldn't record anything
+    /// doesn't start.
+    pub async fn open_audit(&self) -> std::io::Result<()> {
+        let _lock = self.audit.0.lock().await;
+        tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.audit_path(0))
+            .await
+            .map(drop)
+    }
+
+    pub async fn record_audit(&self, entry: &AuditEntry) -> std::io::Result<()> {
+        let mut line = serde_json::to_string(entry).map_err(std::io::Error::other)?;
+        line.push('\n');
+        let _lock = self.audit.0.lock().await;
+        let len = match tokio::fs::metadata(self.audit_path(0)).await {
+            Ok(v) => v.len(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e),
+        };
+        if len > 0 && len + line.len() as u64 > self.config.audit_max_bytes {
+            self.rotate_audit().await?;
+        }
+        let mut f = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.audit_path(0))
+            .await?;
+        f.write_all(line.as_bytes()).await
+    }
+
+    /// Replaces `ips` where they're recorded as callers with their pseudonyms. Returns how many
+    /// entries had one.
+    pub async fn pseudonymize_audit(&self, ips: &HashSet<IpAddr>) -> std::io::Result<u32> {
+        let _lock = self.audit.0.lock().await;
+        let mut count = 0;
+        for n in 0..=self.config.audit_keep {
+            let path = self.audit_path(n);
+            let s = match tokio::fs::read_to_string(&path).await {
+                Ok(v) => v,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            let mut out = String::with_capacity(s.len());
+            let mut changed = false;
+            for line in s.lines().filter(|v| !v.is_empty()) {
+                let mut entry =
+                    serde_json::from_str::<AuditEntry>(line).map_err(std::io::Error::other)?;
+                if let Some(ip) = entry.addr.filter(|v| ips.contains(v)) {
+                    entry.addr = Some(pseudonymize(AddrStorage::Hashed, self.signing_key(), ip));
+                    out += &serde_json::to_string(&entry).map_err(std::io::Error::other)?;
+                    count += 1;
+                    changed = true;
+                } else {
+                    out += line;
+                }
+                out.push('\n');
+            }
+            if changed {
+                // Written aside first, so a failure can't lose the entries.
+                let tmp = path.with_extension("tmp");
+                tokio::fs::write(&tmp, out).await?;
+                tokio::fs::rename(tmp, path).await?;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Entries still kept that `keep` returns true for, oldest first.
+    pub async fn audit_entries(
+        &self,
+        keep: impl Fn(&AuditEntry) -> bool,
+    ) -> std::io::Result<Vec<AuditEntry>> {
+        let _lock = self.audit.0.lock().await;
+        let mut entries = Vec::new();
+        for n in (0..=self.config.audit_keep).rev() {
+            let s = match tokio::fs::read_to_string(self.audit_path(n)).await {
+                Ok(v) => v,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            for line in s.lines().filter(|v| !v.is_empty()) {
+                let entry = serde_json::from_str(line).map_err(std::io::Error::other)?;
+                if keep(&entry) {
+                    entries.push(entry);
+                }
+            }
+        }
+        Ok(entries)
+    }
+}
+
+/// Records every call that isn't a `GET`, `HEAD` or `OPTIONS` in the audit log once it's
+/// answered, including those turned away for a missing or invalid token. A call that can't be
+/// recorded is answered with an error, whatever it did.
+pub struct Audit;
+
+impl<S, B> Transform<S, ServiceRequest> for Audit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Error = Error;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+    type InitError = ();
+    type Response = ServiceResponse<B>;
+    type Transform = AuditMiddleware<S>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuditMiddleware { service }))
+    }
+}
+
+pub struct AuditMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for AuditMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+    type Response = ServiceResponse<B>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if !is_mutating(req.method()) {
+            return Box::pin(self.service.call(req));
+        }
+        let state = req
+            .app_data::<web::Data<AppState>>()
+            .expect("AppState not registered")
+            .clone();
+        // Worked out here rather than taken from what `Require` finds, so calls it turns away
+        // are attributed too.
+        let by = crate::auth::bearer_token(&req)
+            .and_then(|v| state.authenticate(&v))
+            .map(|v| v.name);
+        let addr = req.peer_addr().map(|v| v.ip());
+        let action = format!("{} {}", req.method(), req.path());
+        let target = target(req.path());
+        let fut = self.service.call(req);
+        Box::pin(async move {
+            let res = fut.await;
+            // Routes that take the token some other way add who they found to the request.
+            let by = by.or_else(|| {
+                let res = res.as_ref().ok()?;
+                let ext = res.request().extensions();
+                ext.get::<crate::auth::Identity>().map(|v| v.name.clone())
+            });
+            let status = match &res {
+                Ok(v) => v.status(),
+                Err(e) => e.as_response_error().status_code(),
+            };
+            let entry = AuditEntry {
+                at: crate::state::unix_micros(),
+                by,
+                addr,
+                action,
+                target,
+                status: status.as_u16(),
+            };
+            if let Err(e) = state.record_audit(&entry).await {
+                return Err(actix_web::error::ErrorInternalServerError(format!(
+                    "Failed writing audit log: {e}"
+                )));
+            }
+            res
+        })
+    }
+}
@@ -0,0 +1,204 @@
+use std::{
+    future::{ready, Ready},
+    path::Path,
+};
+
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    error::{ErrorForbidden, InternalError},
+    http::header,
+    web, Error, HttpMessage, HttpResponse,
+};
+use futures_util::future::LocalBoxFuture;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Scope {
+    Read,
+    Save,
+    Delete,
+    /// Implies every other scope.
+    Admin,
+}
+
+/// A bearer token, from `tokens` in the config or from `token_file`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub name: String,
+    pub token: String,
+    pub scopes: Vec<Scope>,
+    /// Unix timestamp in seconds after which the token is rejected.
+    #[serde(default)]
+    pub expires: Option<u64>,
+}
+
+impl ApiToken {
+    fn is_expired(&self) -> bool {
+        self.expires.is_some_and(|expires| {
+            std::time::SystemTime::now()
+                >= std::time::UNIX_EPOCH + std::time::Duration::from_secs(expires)
+        })
+    }
+}
+
+/// Who made a request. Inserted into the request extensions by [`Require`], so handlers can
+/// take it as `web::ReqData<Identity>`.
+#[derive(Debug, Clone)]
+pub struct Identity {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    /// Set for claim tokens, which only grant access to the log with this ID.
+    pub log: Option<u64>,
+}
+
+impl Identity {
+    /// A caller without a token, on routes that allow that.
+    pub fn anonymous() -> Self {
+        Self {
+            name: "anonymous".to_owned(),
+            scopes: Vec::new(),
+            log: None,
+        }
+    }
+
+    pub fn allows(&self, scope: Scope) -> bool {
+        self.scopes.iter().any(|&v| v == scope || v == Scope::Admin)
+    }
+}
+
+/// Loads the extra tokens kept in `token_file`, a RON list of [`ApiToken`]s.
+pub async fn load_token_file(path: &Path) -> std::io::Result<Vec<ApiToken>> {
+    let s = tokio::fs::read_to_string(path).await?;
+    ron::de::from_str(&s).map_err(std::io::Error::other)
+}
+
+impl crate::state::AppState {
+    pub fn authenticate(&self, token: &str) -> Option<Identity> {
+        if let Some(id) = crate::signing::verify_claim_token(self.signing_key(), token) {
+            return Some(Identity {
+                name: format!("claim:{id}"),
+                scopes: vec![Scope::Read, Scope::Delete],
+                log: Some(id),
+            });
+        }
+        self.tokens
+            .iter()
+            .find(|v| crate::signing::constant_time_eq(v.token.as_bytes(), token.as_bytes()))
+            .filter(|v| !v.is_expired())
+            .map(|v| Identity {
+                name: v.name.clone(),
+                scopes: v.scopes.clone(),
+                log: None,
+            })
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenQuery {
+    access_token: String,
+}
+
+/// The `Authorization: Bearer` token, or the `access_token` query parameter for clients that
+/// can't set headers, like the browser's `EventSource`.
+pub fn bearer_token(req: &ServiceRequest) -> Option<String> {
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok()?.strip_prefix("Bearer "))
+        .map(|v| v.trim().to_owned())
+        .or_else(|| {
+            web::Query::<TokenQuery>::from_query(req.query_string())
+                .ok()
+                .map(|v| v.into_inner().access_token)
+        })
+}
+
+fn unauthorized() -> Error {
+    InternalError::from_response(
+        "Missing or invalid token",
+        HttpResponse::Unauthorized()
+            .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+            .finish(),
+    )
+    .into()
+}
+
+fn check(req: &ServiceRequest, scope: Scope) -> Result<Identity, Error> {
+    let state = req
+        .app_data::<web::Data<crate::state::AppState>>()
+        .expect("AppState not registered");
+    let Some(token) = bearer_token(req) else {
+        return if scope == Scope::Read && !state.config.require_read_auth {
+            Ok(Identity::anonymous())
+        } else {
+            Err(unauthorized())
+        };
+    };
+    let identity = state.authenticate(&token).ok_or_else(unauthorized)?;
+    if !identity.allows(scope) {
+        return Err(ErrorForbidden(format!(
+            "Token {:?} lacks the {scope:?} scope",
+            identity.name
+        )));
+    }
+    if let Some(log) = identity.log {
+        if req.match_info().get("id").and_then(|v| v.parse().ok()) != Some(log) {
+            return Err(ErrorForbidden(format!(
+                "Token {:?} only grants access to log {log}",
+                identity.name
+            )));
+        }
+    }
+    Ok(identity)
+}
+
+/// Rejects requests without a bearer token carrying `scope`. Every protected route goes through
+/// this, via `wrap = "Require(Scope::...)"` on the route macro. `Read` routes let anonymous
+/// callers through unless `require_read_auth` is set, and claim tokens only pass on routes for
+/// their own log's `{id}`.
+pub struct Require(pub Scope);
+
+impl<S, B> Transform<S, ServiceRequest> for Require
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Error = Error;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+    type InitError = ();
+    type Response = ServiceResponse<B>;
+    type Transform = RequireMiddleware<S>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequireMiddleware {
+            service,
+            scope: self.0,
+        }))
+    }
+}
+
+pub struct RequireMiddleware<S> {
+    service: S,
+    scope: Scope,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+    type Response = ServiceResponse<B>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        match check(&req, self.scope) {
+            Ok(identity) => {
+                req.extensions_mut().insert(identity);
+                Box::pin(self.service.call(req))
+            }
+            Err(e) => Box::pin(async move { Err(e) }),
+        }
+    }
+}
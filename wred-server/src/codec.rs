@@ -0,0 +1,109 @@
+use actix_web::{
+    body::BoxBody,
+    dev::Payload,
+    http::header::{self, Accept, Header},
+    web, FromRequest, HttpRequest, HttpResponse, Responder,
+};
+use futures_util::future::LocalBoxFuture;
+use serde::{de::DeserializeOwned, Serialize};
+
+pub const POSTCARD: &str = "application/x-postcard";
+pub const JSON: &str = "application/json";
+
+/// Wire format of a request or response body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Postcard,
+    Json,
+}
+
+impl Format {
+    /// Format of the request body. Anything that isn't JSON is taken as postcard, as older
+    /// clients send postcard labelled as plain text.
+    pub fn of_body(req: &HttpRequest) -> Self {
+        req.headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(';').next())
+            .map_or(Self::Postcard, |v| {
+                if v.trim().eq_ignore_ascii_case(JSON) {
+                    Self::Json
+                } else {
+                    Self::Postcard
+                }
+            })
+    }
+
+    /// Format the client prefers for the response, defaulting to postcard.
+    pub fn accepted(req: &HttpRequest) -> Self {
+        Accept::parse(req)
+            .ok()
+            .and_then(|accept| {
+                accept.ranked().into_iter().find_map(|mime| {
+                    match (mime.type_().as_str(), mime.subtype().as_str()) {
+                        ("application", "json") => Some(Self::Json),
+                        ("application", "x-postcard") | ("*", "*") => Some(Self::Postcard),
+                        _ => None,
+                    }
+                })
+            })
+            .unwrap_or(Self::Postcard)
+    }
+
+    pub const fn content_type(self) -> &'static str {
+        match self {
+            Self::Postcard => POSTCARD,
+            Self::Json => JSON,
+        }
+    }
+
+    pub fn encode<T: Serialize>(self, v: &T) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Postcard => postcard::to_allocvec(v).map_err(|e| e.to_string()),
+            Self::Json => serde_json::to_vec(v).map_err(|e| e.to_string()),
+        }
+    }
+
+    pub fn decode<T: DeserializeOwned>(self, v: &[u8]) -> Result<T, String> {
+        match self {
+            Self::Postcard => postcard::from_bytes(v).map_err(|e| e.to_string()),
+            Self::Json => serde_json::from_slice(v).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// A body in whichever format the client negotiated; usable as both extractor and responder.
+#[derive(Debug)]
+pub struct Encoded<T>(pub T);
+
+impl<T: DeserializeOwned + 'static> FromRequest for Encoded<T> {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let format = Format::of_body(req);
+        let body = web::Bytes::from_request(req, payload);
+        Box::pin(async move {
+            format
+                .decode(&body.await?)
+                .map(Self)
+                .map_err(actix_web::error::ErrorBadRequest)
+        })
+    }
+}
+
+impl<T: Serialize> Responder for Encoded<T> {
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse {
+        let format = Format::accepted(req);
+        format.encode(&self.0).map_or_else(
+            |e| HttpResponse::InternalServerError().body(format!("Failed to serialise: {e}")),
+            |v| {
+                HttpResponse::Ok()
+                    .content_type(format.content_type())
+                    .body(v)
+            },
+        )
+    }
+}
@@ -0,0 +1,129 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use wred_server::{DiffLine, DiffTag, LogDiff, NumberedLine};
+
+/// Rewrites matches of `pattern` to `replace` before lines are compared, so volatile tokens
+/// like timestamps and pointers don't show up as differences.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Normalizer {
+    pub pattern: String,
+    #[serde(default)]
+    pub replace: String,
+}
+
+impl Normalizer {
+    fn new(pattern: &str, replace: &str) -> Self {
+        Self {
+            pattern: pattern.to_owned(),
+            replace: replace.to_owned(),
+        }
+    }
+}
+
+/// Timestamps, kernel uptimes, pointers and bare 64-bit addresses.
+pub fn default_normalizers() -> Vec<Normalizer> {
+    vec![
+        Normalizer::new(
+            r"\b\d{4}-\d{2}-\d{2}[ T]\d{2}:\d{2}:\d{2}(\.\d+)?\b",
+            "<time>",
+        ),
+        Normalizer::new(r"\b\d{2}:\d{2}:\d{2}(\.\d+)?\b", "<time>"),
+        Normalizer::new(r"^\[\s*\d+\.\d+\]", "[<uptime>]"),
+        Normalizer::new(r"\b0x[[:xdigit:]]{8,16}\b", "0x<ptr>"),
+        Normalizer::new(r"\b[[:xdigit:]]{16}\b", "<ptr>"),
+    ]
+}
+
+/// Compiles the configured normalizers.
+///
+/// # Panics
+///
+/// If a pattern isn't a valid regex.
+pub fn compile(normalizers: &[Normalizer]) -> Vec<(Regex, String)> {
+    normalizers
+        .iter()
+        .map(|v| {
+            let re = Regex::new(&v.pattern)
+                .unwrap_or_else(|e| panic!("Bad diff normalizer {:?}: {e}", v.pattern));
+            (re, v.replace.clone())
+        })
+        .collect()
+}
+
+pub fn normalize(line: &str, normalizers: &[(Regex, String)]) -> String {
+    normalizers
+        .iter()
+        .fold(line.to_owned(), |line, (re, replace)| {
+            re.replace_all(&line, replace.as_str()).into_owned()
+        })
+}
+
+fn numbered(lines: &[&str], i: usize) -> NumberedLine {
+    NumberedLine {
+        number: u32::try_from(i + 1).unwrap_or(u32::MAX),
+        text: lines[i].to_owned(),
+    }
+}
+
+/// Diffs `old` against `new` line by line, comparing lines after applying `normalizers`.
+/// With `context`, unchanged lines further than that from a change are left out.
+pub fn diff(
+    old: &str,
+    new: &str,
+    normalizers: &[(Regex, String)],
+    context: Option<usize>,
+) -> LogDiff {
+    let old = old.lines().collect::<Vec<_>>();
+    let new = new.lines().collect::<Vec<_>>();
+    let old_norm = old
+        .iter()
+        .map(|v| normalize(v, normalizers))
+        .collect::<Vec<_>>();
+    let new_norm = new
+        .iter()
+        .map(|v| normalize(v, normalizers))
+        .collect::<Vec<_>>();
+
+    let mut lines = Vec::with_capacity(old.len().max(new.len()));
+    for op in similar::capture_diff_slices(similar::Algorithm::Myers, &old_norm, &new_norm) {
+        for change in op.iter_changes(&old_norm, &new_norm) {
+            let tag = match change.tag() {
+                similar::ChangeTag::Equal => DiffTag::Equal,
+                similar::ChangeTag::Delete => DiffTag::Delete,
+                similar::ChangeTag::Insert => DiffTag::Insert,
+            };
+            lines.push(DiffLine {
+                tag,
+                old: change.old_index().map(|i| numbered(&old, i)),
+                new: change.new_index().map(|i| numbered(&new, i)),
+            });
+        }
+    }
+
+    if let Some(context) = context {
+        // Distance to the nearest change, looking both ways.
+        let mut distance = vec![usize::MAX; lines.len()];
+        let mut last = None;
+        for (i, line) in lines.iter().enumerate() {
+            if line.tag != DiffTag::Equal {
+                last = Some(i);
+            }
+            distance[i] = last.map_or(usize::MAX, |last| i - last);
+        }
+        last = None;
+        for (i, line) in lines.iter().enumerate().rev() {
+            if line.tag != DiffTag::Equal {
+                last = Some(i);
+            }
+            distance[i] = distance[i].min(last.map_or(usize::MAX, |last| last - i));
+        }
+        let mut distance = distance.into_iter();
+        lines.retain(|_| distance.next().unwrap() <= context);
+    }
+
+    let first_change = lines.iter().position(|v| v.tag != DiffTag::Equal);
+    LogDiff {
+        lines,
+        first_change,
+    }
+}
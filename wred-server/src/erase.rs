@@ -0,0 +1,429 @@
+//! Erasure of what's stored about a tester when they ask: their logs, with the comments on them,
+//! regressions found against them and duplicates marked of them, which address their pseudonym
+//! stood for, and their address in the audit log. Each erasure is recorded in `erasures.ron` in
+//! the log directory.
+
+use std::{collections::HashSet, net::IpAddr, path::PathBuf};
+
+use wred_server::{
+    ErasedLog, ErasureReport, ErasureSubject, HistoryEntry, LogEvent, Status, TriageChange,
+};
+
+use crate::{
+    addrs::{pseudonymize, AddrStorage},
+    state::AppState,
+};
+
+/// Serializes updating the record of erasures.
+#[derive(Debug, Default)]
+pub struct ErasureLog(tokio::sync::Mutex<()>);
+
+/// Logs an [`ErasureSubject`] covers.
+struct Found {
+    ids: Vec<u64>,
+    /// Logs that may or may not be the subject's.
+    ambiguous: Vec<u64>,
+    /// The subject as the report names it.
+    name: String,
+    addr: Option<IpAddr>,
+}
+
+impl AppState {
+    fn erasures_path(&self) -> PathBuf {
+        self.config.log_dir.join("erasures.ron")
+    }
+
+    async fn read_erasures(&self) -> std::io::Result<Vec<ErasureReport>> {
+        match tokio::fs::read_to_string(self.erasures_path()).await {
+            Ok(v) => ron::de::from_str(&v).map_err(std::io::Error::other),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Erasures so far, oldest first.
+    pub async fn erasure_reports(&self) -> std::io::Result<Vec<ErasureReport>> {
+        let _lock = self.erasures.0.lock().await;
+        self.read_erasures().await
+    }
+
+    fn find_addr(&self, ip: IpAddr) -> Found {
+        // Whatever the server stores now, logs may have been stored another way before.
+        let exact = [
+            ip,
+            pseudonymize(AddrStorage::Hashed, self.signing_key(), ip),
+        ];
+        let truncated = pseudonymize(AddrStorage::Truncated, self.signing_key(), ip);
+        // A log stored as the network is only the subject's if the mappings show the subject
+        // was the only address on it to connect since the log started. Mappings are kept from
+        // `cutoff` on, so logs started before could be anyone's on the network.
+        let cutoff = self.addr_map_cutoff();
+        let mappings = self
+            .addr_mappings(Some(truncated))
+            .into_iter()
+            .filter(|v| v.last_seen > cutoff)
+            .collect::<Vec<_>>();
+        let exclusive = !mappings.is_empty() && mappings.iter().all(|v| v.addr == ip);
+        let mut found = Found {
+            ids: Vec::new(),
+            ambiguous: Vec::new(),
+            name: crate::signing::pseudonym(self.signing_key(), ip),
+            addr: Some(ip),
+        };
+        let logs = self.logs.lock().unwrap();
+        for (&id, v) in logs.iter() {
+            if exact.contains(&v.addr.ip()) {
+                found.ids.push(id);
+            } else if v.addr.ip() == truncated {
+                let started = v.received.first().map_or(v.last_updated, |v| v.at);
+                if exclusive && started > cutoff {
+                    found.ids.push(id);
+                } else {
+                    found.ambiguous.push(id);
+                }
+            }
+        }
+        drop(logs);
+        found
+    }
+
+    fn find_subject(&self, subject: &ErasureSubject) -> Result<Found, String> {
+        let found = match subject {
+            ErasureSubject::Addr(v) => {
+                let ip = v
+                    .parse::<IpAddr>()
+                    .or_else(|_| v.parse::<std::net::SocketAddr>().map(|v| v.ip()))
+                    .map_err(|_| format!("Not an IP address: {v:?}"))?;
+                self.find_addr(ip)
+            }
+            ErasureSubject::SessionCode(v) => {
+                let code = wred_server::parse_session_code(v)
+                    .ok_or_else(|| format!("Not a session code: {v:?}"))?;
+                let mut ids = self
+                    .logs
+                    .lock()
+                    .unwrap()
+                    .keys()
+                    .copied()
+                    .filter(|&id| wred_server::session_code(id) == code)
+                    .collect::<Vec<_>>();
+                // Codes are short enough for logs to share one, and then there's no telling
+                // which is the subject's.
+                let ambiguous = if ids.len() > 1 {
+                    std::mem::take(&mut ids)
+                } else {
+                    Vec::new()
+                };
+                Found {
+                    ids,
+                    ambiguous,
+                    name: code,
+                    addr: None,
+                }
+            }
+            ErasureSubject::ClaimToken(v) => {
+                let id = crate::signing::verify_claim_token(self.signing_key(), v)
+                    .ok_or("Not a valid claim token")?;
+                let exists = self.logs.lock().unwrap().contains_key(&id);
+                Found {
+                    ids: if exists { vec![id] } else { Vec::new() },
+                    ambiguous: Vec::new(),
+                    name: format!("claim token for {}", wred_server::session_code(id)),
+                    addr: None,
+                }
+            }
+        };
+        Ok(found)
+    }
+
+    /// Drops regressions found against any of `baselines`. Returns the IDs of the logs that had
+    /// one.
+    fn clear_regressions(&self, baselines: &HashSet<u64>) -> Vec<u64> {
+        let mut logs = self.logs.lock().unwrap();
+        let cleared = logs
+            .iter_mut()
+            .filter(|(_, v)| {
+                v.regression
+                    .as_ref()
+                    .is_some_and(|v| baselines.contains(&v.baseline))
+            })
+            .map(|(&id, v)| {
+                v.regression = None;
+                self.notify(LogEvent::Session(self.partial(id, v)));
+                id
+            })
+            .collect();
+        drop(logs);
+        cleared
+    }
+
+    /// Moves logs marked as duplicates of any of `originals` back to `New`, as `by`. Returns
+    /// their IDs.
+    fn clear_duplicates(&self, originals: &HashSet<u64>, by: &str) -> Vec<u64> {
+        let mut logs = self.logs.lock().unwrap();
+        let cleared = logs
+            .iter_mut()
+            .filter(|(_, v)| {
+                matches!(v.triage.status, Status::DuplicateOf(original)
+                    if originals.contains(&original))
+            })
+            .map(|(&id, v)| {
+                v.triage.apply(HistoryEntry {
+                    at: crate::state::unix_micros(),
+                    by: by.to_owned(),
+                    change: TriageChange::Status(Status::New),
+                });
+                self.notify(LogEvent::Session(self.partial(id, v)));
+                id
+            })
+            .collect();
+        drop(logs);
+        cleared
+    }
+
+    /// Erases everything stored about `subject` and records what was. Returns the record, or
+    /// why `subject` isn't valid.
+    pub async fn erase_subject(
+        &self,
+        subject: &ErasureSubject,
+        by: String,
+    ) -> std::io::Result<Result<ErasureReport, String>> {
+        let found = match self.find_subject(subject) {
+            Ok(v) => v,
+            Err(e) => return Ok(Err(e)),
+        };
+
+        let mut erased = Vec::with_capacity(found.ids.len());
+        // Addresses the audit log may have recorded the subject's calls from.
+        let mut ips = found.addr.into_iter().collect::<HashSet<_>>();
+        for id in found.ids {
+            let was_saved = self.is_saved(id);
+            let Some((bytes, comments, addr)) = self
+                .logs
+                .lock()
+                .unwrap()
+                .get(&id)
+                .map(|v| (v.data.len() as u64, v.comments.len(), v.addr))
+            else {
+                continue;
+            };
+            // Pseudonyms have no port.
+            if addr.port() != 0 {
+                ips.insert(addr.ip());
+            }
+            if self.remove_log(id).await? {
+                erased.push(ErasedLog {
+                    id,
+                    session_code: wred_server::session_code(id),
+                    was_saved,
+                    bytes,
+                    comments: u32::try_from(comments).unwrap_or(u32::MAX),
+                });
+            }
+        }
+        let erased_ids = erased.iter().map(|v| v.id).collect();
+        let regressions_cleared = self.clear_regressions(&erased_ids);
+        crate::analyzer::persist(self, regressions_cleared.clone()).await?;
+        let duplicates_cleared = self.clear_duplicates(&erased_ids, &by);
+        crate::analyzer::persist(self, duplicates_cleared.clone()).await?;
+        let audit_entries = self.pseudonymize_audit(&ips).await?;
+        let addr_mappings = match found.addr {
+            Some(ip) => self.forget_addr(ip).await?,
+            None => 0,
+        };
+
+        let report = ErasureReport {
+            at: crate::state::unix_micros(),
+            by,
+            subject: found.name,
+            logs: erased,
+            addr_mappings,
+            regressions_cleared,
+            duplicates_cleared,
+            audit_entries,
+            signatures: self.signatures_drafted_from(&erased_ids),
+            ambiguous: found.ambiguous,
+        };
+        let _lock = self.erasures.0.lock().await;
+        let mut reports = self.read_erasures().await?;
+        reports.push(report.clone());
+        let s = ron::ser::to_string_pretty(&reports, ron::ser::PrettyConfig::default())
+            .map_err(std::io::Error::other)?;
+        tokio::fs::write(self.erasures_path(), s).await?;
+        Ok(Ok(report))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, net::SocketAddr};
+
+    use wred_server::LogEntry;
+
+    use super::*;
+    use crate::state::ServerConfig;
+
+    fn state(name: &str) -> AppState {
+        let log_dir = std::env::temp_dir().join(format!("wred-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&log_dir).unwrap();
+        let config = ServerConfig {
+            log_dir,
+            stored_addr: AddrStorage::Truncated,
+            addr_map_days: 30,
+            audit_max_bytes: 1 << 20,
+            ..ServerConfig::default()
+        };
+        AppState::new(config, vec![], vec![], b"key".to_vec())
+    }
+
+    /// Adds a log from `addr`, as the server would store it, started at `at`.
+    async fn add_log(state: &AppState, id: u64, addr: &str, at: u64) {
+        let addr = state.store_addr(addr.parse().unwrap()).await.unwrap();
+        let log = LogEntry::new(at, addr, String::new());
+        state.logs.lock().unwrap().insert(id, log);
+    }
+
+    fn find(state: &AppState, subject: &ErasureSubject) -> (Vec<u64>, Vec<u64>) {
+        let mut found = state.find_subject(subject).unwrap();
+        found.ids.sort_unstable();
+        found.ambiguous.sort_unstable();
+        (found.ids, found.ambiguous)
+    }
+
+    fn addr(v: &str) -> ErasureSubject {
+        ErasureSubject::Addr(v.to_owned())
+    }
+
+    #[actix_web::test]
+    async fn finds_exact_addrs() {
+        let state = state("exact");
+        let now = crate::state::unix_micros();
+        let full = SocketAddr::from(([10, 0, 0, 1], 5000));
+        let hashed = pseudonymize(AddrStorage::Hashed, state.signing_key(), full.ip());
+        let logs = [
+            (1, full),
+            (2, SocketAddr::new(hashed, 0)),
+            (3, "10.0.0.2:1".parse().unwrap()),
+        ];
+        for (id, addr) in logs {
+            state
+                .logs
+                .lock()
+                .unwrap()
+                .insert(id, LogEntry::new(now, addr, String::new()));
+        }
+        assert_eq!(find(&state, &addr("10.0.0.1")), (vec![1, 2], vec![]));
+        assert_eq!(find(&state, &addr("10.0.0.1:9")), (vec![1, 2], vec![]));
+        assert_eq!(find(&state, &addr("10.0.0.3")), (vec![], vec![]));
+        assert!(state.find_subject(&addr("nope")).is_err());
+        std::fs::remove_dir_all(&state.config.log_dir).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn finds_truncated_addrs_only_if_exclusive() {
+        let state = state("truncated");
+        let now = crate::state::unix_micros();
+        add_log(&state, 1, "10.0.0.1:1", now).await;
+        // Started before mappings were kept, so anyone on the network could have sent it.
+        add_log(&state, 2, "10.0.0.1:1", 0).await;
+        assert_eq!(find(&state, &addr("10.0.0.1")), (vec![1], vec![2]));
+        assert_eq!(find(&state, &addr("10.0.0.9")), (vec![], vec![1, 2]));
+
+        add_log(&state, 3, "10.0.0.2:1", now).await;
+        assert_eq!(find(&state, &addr("10.0.0.1")), (vec![], vec![1, 2, 3]));
+        assert_eq!(find(&state, &addr("10.0.1.1")), (vec![], vec![]));
+        std::fs::remove_dir_all(&state.config.log_dir).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn finds_session_codes_and_claims() {
+        let state = state("codes");
+        let now = crate::state::unix_micros();
+        // Two IDs that share a code.
+        let mut codes = HashMap::new();
+        let (a, b) = (0..1 << 20)
+            .find_map(|id| {
+                codes
+                    .insert(wred_server::session_code(id), id)
+                    .map(|v| (v, id))
+            })
+            .unwrap();
+        let other = (0..3).find(|v| ![a, b].contains(v)).unwrap();
+        for id in [a, b, other] {
+            add_log(&state, id, "10.0.0.1:1", now).await;
+        }
+
+        let code = |id| ErasureSubject::SessionCode(wred_server::session_code(id).to_lowercase());
+        assert_eq!(find(&state, &code(other)), (vec![other], vec![]));
+        let mut shared = vec![a, b];
+        shared.sort_unstable();
+        assert_eq!(find(&state, &code(a)), (vec![], shared));
+        assert!(state
+            .find_subject(&ErasureSubject::SessionCode("nope".to_owned()))
+            .is_err());
+
+        let claim =
+            |id| ErasureSubject::ClaimToken(crate::signing::claim_token(state.signing_key(), id));
+        assert_eq!(find(&state, &claim(a)), (vec![a], vec![]));
+        assert_eq!(find(&state, &claim(u64::MAX)), (vec![], vec![]));
+        let forged = ErasureSubject::ClaimToken(format!("claim.{a}.00"));
+        assert!(state.find_subject(&forged).is_err());
+        std::fs::remove_dir_all(&state.config.log_dir).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn clears_what_refers_to_erased_logs() {
+        let state = state("refs");
+        let now = crate::state::unix_micros();
+        add_log(&state, 1, "10.0.0.1:1", now).await;
+        add_log(&state, 2, "10.1.0.1:1", now).await;
+        state
+            .logs
+            .lock()
+            .unwrap()
+            .get_mut(&2)
+            .unwrap()
+            .triage
+            .status = Status::DuplicateOf(1);
+        let new = wred_server::NewSignature {
+            title: "Crash".to_owned(),
+            patterns: vec!["panic".to_owned()],
+            drafted_from: Some(1),
+            ..wred_server::NewSignature::default()
+        };
+        let signature = state.add_signature(new, "admin".to_owned()).await.unwrap();
+        let audited = ["10.0.0.1", "10.0.0.2"].map(|v| v.parse::<IpAddr>().unwrap());
+        for addr in audited {
+            let entry = wred_server::AuditEntry {
+                at: now,
+                by: None,
+                addr: Some(addr),
+                action: "POST /api/v1/1/save".to_owned(),
+                target: Some(1),
+                status: 200,
+            };
+            state.record_audit(&entry).await.unwrap();
+        }
+
+        let report = state
+            .erase_subject(&addr("10.0.0.1"), "admin".to_owned())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(report.logs.iter().map(|v| v.id).collect::<Vec<_>>(), [1]);
+        assert_eq!(report.duplicates_cleared, [2]);
+        assert_eq!(state.logs.lock().unwrap()[&2].triage.status, Status::New);
+        assert_eq!(report.signatures, [signature.unwrap().id]);
+        assert_eq!(report.audit_entries, 1);
+        let addrs = state
+            .audit_entries(|_| true)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|v| v.addr.unwrap())
+            .collect::<Vec<_>>();
+        let hashed = pseudonymize(AddrStorage::Hashed, state.signing_key(), audited[0]);
+        assert_eq!(addrs, [hashed, audited[1]]);
+        std::fs::remove_dir_all(&state.config.log_dir).unwrap();
+    }
+}
@@ -0,0 +1,346 @@
+//! Downloads of a log as a file. Logs are read a chunk at a time, so a large one is neither
+//! copied while the log mutex is held nor kept in memory in full for the response.
+
+use std::{fmt::Write as _, io::Write as _, sync::Arc};
+
+use actix_web::web::Bytes;
+use flate2::{write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+
+use crate::state::AppState;
+
+/// Bytes of log read per chunk.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+const HTML_STYLE: &str =
+    "body{font-family:sans-serif;background:#1b1b1b;color:#ddd}table{border-collapse:collapse;\
+     font-family:monospace;white-space:pre-wrap}td{padding:0 \
+     .5em;vertical-align:top}.n{color:#777;text-align:right;user-select:none}.n \
+     a{color:inherit;text-decoration:none}.m{color:#8ab4f8;font-weight:bold}.f{color:#c3a6ff}.\
+     p{color:#777}.error{background:#4a1f1f}.warn{background:#4a421f}";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// The log as it was received.
+    #[default]
+    Text,
+    /// The log as it was received, gzipped.
+    Gzip,
+    /// One JSON object per line, with when it was received and its parts split out.
+    Jsonl,
+    /// A standalone page with line numbers and errors and warnings highlighted.
+    Html,
+}
+
+impl Format {
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Text => "log",
+            Self::Gzip => "log.gz",
+            Self::Jsonl => "jsonl",
+            Self::Html => "html",
+        }
+    }
+
+    pub const fn content_type(self) -> &'static str {
+        match self {
+            Self::Text => "text/plain; charset=utf-8",
+            Self::Gzip => "application/gzip",
+            Self::Jsonl => "application/x-ndjson",
+            Self::Html => "text/html; charset=utf-8",
+        }
+    }
+}
+
+/// A line of a JSON lines export.
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    /// 1-based.
+    line: u32,
+    /// Byte offset of the line in the log.
+    offset: u64,
+    /// When the chunk the line starts in was received, as a Unix timestamp in microseconds, if
+    /// the server recorded it.
+    at: Option<u64>,
+    prefix: &'a str,
+    subsystem: Option<&'a str>,
+    function: Option<&'a str>,
+    severity: Option<wred_server::Severity>,
+    message: &'a str,
+    text: &'a str,
+}
+
+fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// A download in progress.
+pub struct Export {
+    data: Arc<AppState>,
+    id: u64,
+    format: Format,
+    title: String,
+    /// Offset of the next byte to read.
+    pos: usize,
+    /// Length of the log when the download started. Anything appended later is left out.
+    end: usize,
+    /// When the chunks read so far were received, trimmed to those still needed.
+    received: Vec<wred_server::Received>,
+    /// Incomplete last line read so far, and its offset, for the line-based formats and for
+    /// redacting the others.
+    partial: String,
+    partial_offset: u64,
+    line: u32,
+    gzip: Option<GzEncoder<Vec<u8>>>,
+    started: bool,
+    done: bool,
+}
+
+impl Export {
+    /// Starts a download of log `id`, or returns `None` if there's no such log.
+    pub fn new(data: Arc<AppState>, id: u64, format: Format) -> Option<Self> {
+        let logs = data.logs.lock().unwrap();
+        let v = logs.get(&id)?;
+        let end = v.data.len();
+        let title = v.meta.title.clone();
+        drop(logs);
+        Some(Self {
+            data,
+            id,
+            format,
+            title,
+            pos: 0,
+            end,
+            received: Vec::new(),
+            partial: String::new(),
+            partial_offset: 0,
+            line: 0,
+            gzip: (format == Format::Gzip)
+                .then(|| GzEncoder::new(Vec::new(), Compression::default())),
+            started: false,
+            done: false,
+        })
+    }
+
+    pub fn file_name(&self) -> String {
+        format!(
+            "{}.{}",
+            wred_server::session_code(self.id),
+            self.format.extension()
+        )
+    }
+
+    /// Copies the next chunk of the log. Returns `None` once it's all read, or an error if the
+    /// log was deleted or shrank in the meantime.
+    fn read(&mut self) -> std::io::Result<Option<String>> {
+        if self.pos >= self.end {
+            return Ok(None);
+        }
+        let data = Arc::clone(&self.data);
+        let logs = data.logs.lock().unwrap();
+        let Some(v) = logs.get(&self.id) else {
+            return Err(std::io::Error::other("Log deleted during the download"));
+        };
+        if self.pos >= v.data.len() || !v.data.is_char_boundary(self.pos) {
+            return Err(std::io::Error::other("Log shrank during the download"));
+        }
+        let mut end = (self.pos + CHUNK_SIZE).min(self.end).min(v.data.len());
+        while !v.data.is_char_boundary(end) {
+            end -= 1;
+        }
+        let chunk = v.data[self.pos..end].to_owned();
+        if self.format == Format::Jsonl {
+            self.copy_received(&v.received, end);
+        }
+        drop(logs);
+        self.pos = end;
+        Ok(Some(chunk))
+    }
+
+    /// Keeps when the chunks from `pos` up to `end` were received.
+    fn copy_received(&mut self, received: &[wred_server::Received], end: usize) {
+        let first = received
+            .partition_point(|r| r.offset <= self.pos as u64)
+            .saturating_sub(1);
+        let last = received.partition_point(|r| r.offset < end as u64);
+        let known = self.received.last().map(|r| r.offset);
+        self.received.extend(
+            received[first..last.max(first)]
+                .iter()
+                .filter(|r| known.is_none_or(|known| r.offset > known)),
+        );
+    }
+
+    /// When the data at `offset` was received, forgetting what earlier lines needed.
+    fn received_at(&mut self, offset: u64) -> Option<u64> {
+        let i = self.received.partition_point(|r| r.offset <= offset);
+        self.received.drain(..i.saturating_sub(1));
+        self.received
+            .first()
+            .filter(|r| r.offset <= offset)
+            .map(|r| r.at)
+    }
+
+    fn write_line(&mut self, out: &mut String, offset: u64, text: &str) {
+        self.line += 1;
+        let text = self
+            .data
+            .redactors
+            .redact(text.strip_suffix('\r').unwrap_or(text));
+        let text = text.as_str();
+        let parsed = wred_server::parse::parse_line(self.line, text);
+        match self.format {
+            Format::Jsonl => {
+                let line = JsonLine {
+                    line: self.line,
+                    offset,
+                    at: self.received_at(offset),
+                    prefix: &parsed.prefix,
+                    subsystem: parsed.subsystem.as_deref(),
+                    function: parsed.function.as_deref(),
+                    severity: parsed.severity,
+                    message: &parsed.message,
+                    text,
+                };
+                out.push_str(&serde_json::to_string(&line).unwrap());
+                out.push('\n');
+            }
+            Format::Html => {
+                let class = match parsed.severity {
+                    Some(wred_server::Severity::Error) => " class=\"error\"",
+                    Some(wred_server::Severity::Warning) => " class=\"warn\"",
+                    None => "",
+                };
+                let n = self.line;
+                let _e = write!(
+                    out,
+                    "<tr id=\"L{n}\"{class}><td class=\"n\"><a href=\"#L{n}\">{n}</a></td><td>"
+                );
+                if !parsed.prefix.is_empty() {
+                    let _e = write!(
+                        out,
+                        "<span class=\"p\">{}</span> ",
+                        escape_html(&parsed.prefix)
+                    );
+                }
+                if let Some(subsystem) = &parsed.subsystem {
+                    let _e = write!(out, "<span class=\"m\">{}</span> ", escape_html(subsystem));
+                }
+                if let Some(function) = &parsed.function {
+                    let _e = write!(out, "<span class=\"f\">{}</span> ", escape_html(function));
+                }
+                out.push_str(&escape_html(&parsed.message));
+                out.push_str("</td></tr>\n");
+            }
+            Format::Text | Format::Gzip => unreachable!(),
+        }
+    }
+
+    /// Turns complete lines of `chunk`, or every line if `last`, into the export format.
+    fn write_lines(&mut self, chunk: &str, last: bool) -> String {
+        let mut out = String::new();
+        let mut rest = std::mem::take(&mut self.partial);
+        rest.push_str(chunk);
+        let mut start = 0;
+        while let Some(n) = rest[start..].find('\n') {
+            self.write_line(&mut out, self.partial_offset, &rest[start..start + n]);
+            self.partial_offset += n as u64 + 1;
+            start += n + 1;
+        }
+        if last && start < rest.len() {
+            self.write_line(&mut out, self.partial_offset, &rest[start..]);
+        } else {
+            rest[start..].clone_into(&mut self.partial);
+        }
+        out
+    }
+
+    /// Complete lines of `chunk`, or all of it if `last`, redacted if redaction is on.
+    fn redact_lines(&mut self, chunk: String, last: bool) -> String {
+        if !self.data.redactors.is_on() {
+            return chunk;
+        }
+        let mut rest = std::mem::take(&mut self.partial);
+        rest.push_str(&chunk);
+        let end = if last {
+            rest.len()
+        } else {
+            rest.rfind('\n').map_or(0, |n| n + 1)
+        };
+        self.partial = rest.split_off(end);
+        self.data.redactors.redact(&rest)
+    }
+
+    /// The next part of the file, or `None` once it's all been produced.
+    fn next_part(&mut self) -> Option<std::io::Result<Vec<u8>>> {
+        if self.done {
+            return None;
+        }
+        let chunk = match self.read() {
+            Ok(v) => v,
+            Err(e) => {
+                self.done = true;
+                return Some(Err(e));
+            }
+        };
+        let last = chunk.is_none();
+        self.done = last;
+        let chunk = chunk.unwrap_or_default();
+
+        let mut out = String::new();
+        if !self.started {
+            self.started = true;
+            if self.format == Format::Html {
+                let title = if self.title.is_empty() {
+                    wred_server::session_code(self.id)
+                } else {
+                    format!("{} ({})", self.title, wred_server::session_code(self.id))
+                };
+                let _e = write!(
+                    out,
+                    "<!DOCTYPE html>\n<html><head><meta \
+                     charset=\"utf-8\"><title>{0}</title><style>{HTML_STYLE}</style></\
+                     head><body><h1>{0}</h1><table>\n",
+                    escape_html(&title)
+                );
+            }
+        }
+        match self.format {
+            Format::Text => return Some(Ok(self.redact_lines(chunk, last).into_bytes())),
+            Format::Gzip => {
+                let chunk = self.redact_lines(chunk, last);
+                let gzip = self.gzip.as_mut().unwrap();
+                gzip.write_all(chunk.as_bytes()).unwrap();
+                if last {
+                    return Some(Ok(self.gzip.take().unwrap().finish().unwrap()));
+                }
+                return Some(Ok(std::mem::take(gzip.get_mut())));
+            }
+            Format::Jsonl | Format::Html => out += &self.write_lines(&chunk, last),
+        }
+        if last && self.format == Format::Html {
+            out.push_str("</table></body></html>\n");
+        }
+        Some(Ok(out.into_bytes()))
+    }
+
+    /// The file as a response body. It ends in an error if the log is deleted or shrinks
+    /// before it's all read, so the download fails rather than look complete.
+    pub fn into_stream(self) -> impl futures_util::Stream<Item = std::io::Result<Bytes>> {
+        futures_util::stream::unfold(self, |mut export| async move {
+            let part = export.next_part()?;
+            Some((part.map(Bytes::from), export))
+        })
+    }
+}
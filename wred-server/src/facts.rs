@@ -0,0 +1,163 @@
+//! Facts about the hardware and OS a log was taken on, pulled out of what it prints, so logs can
+//! be told apart by GPU and OS rather than by IP.
+
+use std::collections::{btree_map::Entry, BTreeMap};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use wred_server::BaselineKey;
+
+/// Fact the GPU part of a [`BaselineKey`] comes from.
+pub const DEVICE_ID: &str = "device_id";
+/// Fact the OS part of a [`BaselineKey`] comes from.
+pub const OS_VERSION: &str = "os_version";
+
+/// Pulls a fact out of a log with the first capture group of `pattern`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Extractor {
+    pub fact: String,
+    pub pattern: String,
+    /// Keep every distinct match, separated by commas, rather than the first.
+    #[serde(default)]
+    pub all: bool,
+}
+
+impl Extractor {
+    fn new(fact: &str, pattern: &str, all: bool) -> Self {
+        Self {
+            fact: fact.to_owned(),
+            pattern: pattern.to_owned(),
+            all,
+        }
+    }
+}
+
+/// PCI device ID and revision, firmware names, and macOS, kernel and Lilu versions.
+pub fn default_extractors() -> Vec<Extractor> {
+    vec![
+        Extractor::new(
+            DEVICE_ID,
+            r"(?i)device[-_ ]?id\W{0,3}(?:0x)?([[:xdigit:]]{4})\b",
+            false,
+        ),
+        Extractor::new(
+            "revision",
+            r"(?i)\brev(?:ision)?(?: id)?\W{0,3}(?:0x)?([[:xdigit:]]{1,2})\b",
+            false,
+        ),
+        Extractor::new("firmware", r"\b([\w-]+\.bin)\b", true),
+        Extractor::new(
+            OS_VERSION,
+            r"(?i)macos(?: version)?\s+v?(\d+(?:\.\d+)+)",
+            false,
+        ),
+        Extractor::new(
+            "kernel",
+            r"(?i)darwin kernel version\s+v?(\d+(?:\.\d+)+)",
+            false,
+        ),
+        Extractor::new(
+            "lilu",
+            r"(?i)\blilu\b\W+(?:version\W+)?v?(\d+\.\d+\.\d+)",
+            false,
+        ),
+    ]
+}
+
+#[derive(Debug)]
+pub struct Extractors(Vec<(Regex, Extractor)>);
+
+impl Extractors {
+    /// # Panics
+    ///
+    /// If a pattern isn't a valid regex.
+    pub fn new(extractors: Vec<Extractor>) -> Self {
+        Self(
+            extractors
+                .into_iter()
+                .map(|v| {
+                    let re = Regex::new(&v.pattern)
+                        .unwrap_or_else(|e| panic!("Bad extractor pattern for {:?}: {e}", v.fact));
+                    (re, v)
+                })
+                .collect(),
+        )
+    }
+
+    /// Facts `data` has printed so far. The first extractor to find a fact wins.
+    pub fn extract(&self, data: &str) -> BTreeMap<String, String> {
+        let mut facts = BTreeMap::new();
+        for (re, extractor) in &self.0 {
+            if facts.contains_key(&extractor.fact) {
+                continue;
+            }
+            let mut found = re
+                .captures_iter(data)
+                .filter_map(|v| Some(v.get(1)?.as_str()));
+            let value = if extractor.all {
+                let mut values = Vec::<&str>::new();
+                for v in found {
+                    if !values.contains(&v) {
+                        values.push(v);
+                    }
+                }
+                values.join(", ")
+            } else {
+                found.next().unwrap_or_default().to_owned()
+            };
+            if !value.is_empty() {
+                facts.insert(extractor.fact.clone(), value);
+            }
+        }
+        facts
+    }
+
+    /// Adds facts `found` in data printed after what `facts` were found in. Facts already found
+    /// are kept, with any new values added for extractors that keep every match. Returns
+    /// whether `facts` changed.
+    pub fn merge(
+        &self,
+        facts: &mut BTreeMap<String, String>,
+        found: BTreeMap<String, String>,
+    ) -> bool {
+        let mut changed = false;
+        for (fact, value) in found {
+            match facts.entry(fact) {
+                Entry::Vacant(v) => {
+                    v.insert(value);
+                    changed = true;
+                }
+                Entry::Occupied(mut v) => {
+                    if !self.0.iter().any(|(_, e)| e.all && e.fact == *v.key()) {
+                        continue;
+                    }
+                    let mut values = v.get().split(", ").map(str::to_owned).collect::<Vec<_>>();
+                    let len = values.len();
+                    for value in value.split(", ") {
+                        if !values.iter().any(|v| v == value) {
+                            values.push(value.to_owned());
+                        }
+                    }
+                    if values.len() != len {
+                        *v.get_mut() = values.join(", ");
+                        changed = true;
+                    }
+                }
+            }
+        }
+        changed
+    }
+}
+
+/// The key a log is matched against baselines by, if it has printed both parts yet.
+pub fn baseline_key(facts: &BTreeMap<String, String>) -> Option<BaselineKey> {
+    let device_id = facts.get(DEVICE_ID)?;
+    let device_id = device_id
+        .strip_prefix("0x")
+        .unwrap_or(device_id)
+        .to_ascii_lowercase();
+    Some(BaselineKey {
+        device_id: format!("0x{device_id}"),
+        os_version: facts.get(OS_VERSION)?.clone(),
+    })
+}
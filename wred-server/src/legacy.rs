@@ -0,0 +1,107 @@
+//! The routes from before the API was versioned, kept at the root for clients that predate
+//! `/api/v1`. They speak postcard only, list logs in the old summary shape, and take a token as
+//! a postcard-encoded string body rather than an `Authorization` header. `GET /{id}` is
+//! [`crate::routes::get_log`] itself, as its plain-text answer hasn't changed.
+
+use actix_web::{delete, get, post, web, HttpMessage, HttpRequest, HttpResponse};
+use serde::Serialize;
+use wred_server::Submitter;
+
+use crate::{
+    auth::{Identity, Require, Scope},
+    state::AppState,
+};
+
+/// A log summary as old clients decode it.
+#[derive(Serialize)]
+struct LogEntryPartial {
+    id: u64,
+    last_updated: u64,
+    addr: std::net::SocketAddr,
+    is_saved: bool,
+}
+
+#[get("/all", wrap = "Require(Scope::Read)")]
+#[allow(clippy::unused_async, clippy::future_not_send)]
+async fn get_logs(data: web::Data<AppState>, identity: web::ReqData<Identity>) -> HttpResponse {
+    let logs = data.logs.lock().unwrap();
+    // Old clients can't filter, so archived logs are left out for them.
+    let ents = logs
+        .iter()
+        .filter(|(_, v)| !v.archived)
+        .map(|(&id, v)| {
+            let partial = data.mask_partial(data.partial(id, v), &identity);
+            LogEntryPartial {
+                id,
+                last_updated: partial.last_updated,
+                // Old clients have no way to show an address they aren't told.
+                addr: match partial.addr {
+                    Submitter::Addr(v) => v,
+                    Submitter::Pseudonym(_) | Submitter::Hidden => {
+                        std::net::SocketAddr::from(([0, 0, 0, 0], 0))
+                    }
+                },
+                is_saved: partial.is_saved,
+            }
+        })
+        .collect::<Vec<_>>();
+    drop(logs);
+    postcard::to_allocvec(&ents).map_or_else(
+        |e| HttpResponse::InternalServerError().body(format!("Failed to serialise: {e}")),
+        |v| HttpResponse::Ok().body(v),
+    )
+}
+
+/// Whether the token in `body` is valid, carries `scope` and may act on log `id`. If it is, who
+/// it belongs to is added to the request, so the audit log attributes the call to them.
+fn authorize(req: &HttpRequest, data: &AppState, body: &[u8], scope: Scope, id: u64) -> bool {
+    let Ok(token) = postcard::from_bytes::<String>(body) else {
+        return false;
+    };
+    let Some(identity) = data
+        .authenticate(&token)
+        .filter(|v| v.allows(scope) && v.log.is_none_or(|v| v == id))
+    else {
+        return false;
+    };
+    req.extensions_mut().insert(identity);
+    true
+}
+
+#[delete("/{id:[[:digit:]]+}")]
+#[allow(clippy::future_not_send)]
+async fn delete_log(
+    req: HttpRequest,
+    path: web::Path<u64>,
+    data: web::Data<AppState>,
+    body: web::Bytes,
+) -> std::io::Result<HttpResponse> {
+    let id = path.into_inner();
+    if !authorize(&req, &data, &body, Scope::Delete, id) {
+        return Ok(HttpResponse::Unauthorized().finish());
+    }
+    if data.remove_log(id).await? {
+        Ok(HttpResponse::Ok().finish())
+    } else {
+        Ok(HttpResponse::NotFound().finish())
+    }
+}
+
+#[post("/{id:[[:digit:]]+}")]
+#[allow(clippy::future_not_send)]
+async fn save_log(
+    req: HttpRequest,
+    path: web::Path<u64>,
+    data: web::Data<AppState>,
+    body: web::Bytes,
+) -> std::io::Result<HttpResponse> {
+    let id = path.into_inner();
+    if !authorize(&req, &data, &body, Scope::Save, id) {
+        return Ok(HttpResponse::Unauthorized().finish());
+    }
+    if data.save_log(id).await? {
+        Ok(HttpResponse::Ok().finish())
+    } else {
+        Ok(HttpResponse::NotFound().finish())
+    }
+}
@@ -8,25 +8,825 @@
 )]
 #![allow(clippy::module_name_repetitions)]
 
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    sync::Arc,
+};
+
 use sequence_generator::sequence_generator;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub mod parse;
+pub mod query;
+
+/// Where a session came from, as far as the caller is allowed to know.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum Submitter {
+    #[cfg_attr(feature = "openapi", schema(value_type = String))]
+    Addr(std::net::SocketAddr),
+    /// Stable stand-in for an address the server doesn't disclose.
+    Pseudonym(String),
+    Hidden,
+}
+
+impl std::fmt::Display for Submitter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Addr(v) => v.fmt(f),
+            Self::Pseudonym(v) => f.write_str(v),
+            Self::Hidden => f.write_str("Hidden"),
+        }
+    }
+}
+
+/// Which address a pseudonym the server stored in place of one stands for, while it's kept.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AddrMapping {
+    /// What sessions from `addr` were stored with.
+    #[cfg_attr(feature = "openapi", schema(value_type = String))]
+    pub pseudonym: std::net::IpAddr,
+    #[cfg_attr(feature = "openapi", schema(value_type = String))]
+    pub addr: std::net::IpAddr,
+    /// When `addr` last connected, as a Unix timestamp in microseconds.
+    pub last_seen: u64,
+}
+
+/// Details people attach to a log to keep track of it.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LogMeta {
+    pub title: String,
+    pub notes: String,
+    pub tags: BTreeSet<String>,
+}
+
+/// Changes to a log's [`LogMeta`]. Fields left out are kept as they are.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LogMetaPatch {
+    pub title: Option<String>,
+    pub notes: Option<String>,
+    pub tags: Option<BTreeSet<String>>,
+}
+
+impl LogMeta {
+    pub fn apply(&mut self, patch: LogMetaPatch) {
+        if let Some(v) = patch.title {
+            self.title = v;
+        }
+        if let Some(v) = patch.notes {
+            self.notes = v;
+        }
+        if let Some(v) = patch.tags {
+            self.tags = v;
+        }
+    }
+}
+
+/// Where a log is in triage.
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum Status {
+    #[default]
+    New,
+    Triaging,
+    NeedsInfo,
+    Resolved,
+    /// Same problem as the log with this ID.
+    DuplicateOf(u64),
+}
+
+impl Status {
+    /// Name used in queries, like `status:needs-info`.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::New => "new",
+            Self::Triaging => "triaging",
+            Self::NeedsInfo => "needs-info",
+            Self::Resolved => "resolved",
+            Self::DuplicateOf(_) => "duplicate-of",
+        }
+    }
+}
+
+impl std::fmt::Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DuplicateOf(id) => write!(f, "Duplicate of {}", session_code(*id)),
+            Self::New => f.write_str("New"),
+            Self::Triaging => f.write_str("Triaging"),
+            Self::NeedsInfo => f.write_str("Needs info"),
+            Self::Resolved => f.write_str("Resolved"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum TriageChange {
+    Status(Status),
+    /// `None` when unassigned.
+    Assignee(Option<String>),
+}
+
+/// A recorded triage change.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct HistoryEntry {
+    /// Unix timestamp in microseconds.
+    pub at: u64,
+    /// Name of the token that made the change.
+    pub by: String,
+    pub change: TriageChange,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Triage {
+    pub status: Status,
+    pub assignee: Option<String>,
+    /// Oldest first.
+    pub history: Vec<HistoryEntry>,
+}
+
+impl Triage {
+    pub fn apply(&mut self, entry: HistoryEntry) {
+        match &entry.change {
+            TriageChange::Status(v) => self.status = *v,
+            TriageChange::Assignee(v) => self.assignee.clone_from(v),
+        }
+        self.history.push(entry);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Comment {
+    /// Unique within the log.
+    pub id: u64,
+    /// Name of the token that posted it.
+    pub author: String,
+    /// Unix timestamp in microseconds.
+    pub created: u64,
+    pub edited: Option<u64>,
+    /// Lines the comment is about, if not the whole log.
+    pub lines: Option<LineRange>,
+    pub body: String,
+}
+
+/// A comment to post, or the new contents of one being edited.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CommentBody {
+    pub lines: Option<LineRange>,
+    pub body: String,
+}
+
+/// Logs a bulk operation applies to: a list of IDs, or every log matching a
+/// [`query::Query`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum Selection {
+    Ids(Vec<u64>),
+    Query(String),
+}
+
+/// Tags to add to and remove from every selected log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BulkTags {
+    pub select: Selection,
+    #[serde(default)]
+    pub add: BTreeSet<String>,
+    #[serde(default)]
+    pub remove: BTreeSet<String>,
+}
+
+/// Status to move every selected log to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BulkStatus {
+    pub select: Selection,
+    pub status: Status,
+}
+
+/// Outcome of a bulk operation for one log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BulkResult {
+    pub id: u64,
+    /// Why the operation failed for this log, if it did.
+    pub error: Option<String>,
+}
+
+/// A call to the API that could change something, as the server's audit log records it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AuditEntry {
+    /// Unix timestamp in microseconds.
+    pub at: u64,
+    /// Name of the token the call was made with, if it was a valid one.
+    pub by: Option<String>,
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<String>))]
+    pub addr: Option<std::net::IpAddr>,
+    /// Method and path, like `DELETE /api/v1/123`.
+    pub action: String,
+    /// ID of the log or other thing the path names, if it names one.
+    pub target: Option<u64>,
+    /// HTTP status of the response.
+    pub status: u16,
+}
+
+/// Whose data to erase: a submitter's IP, a session code, or a submitter's claim token.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum ErasureSubject {
+    Addr(String),
+    SessionCode(String),
+    ClaimToken(String),
+}
+
+/// A log erased for an [`ErasureSubject`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ErasedLog {
+    pub id: u64,
+    pub session_code: String,
+    /// Whether its file was deleted too.
+    pub was_saved: bool,
+    pub bytes: u64,
+    pub comments: u32,
+}
+
+/// What an erasure deleted, kept by the server as a record of it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ErasureReport {
+    /// Unix timestamp in microseconds.
+    pub at: u64,
+    /// Name of the token that asked for it.
+    pub by: String,
+    /// The subject, with an address replaced by its pseudonym so the report doesn't keep it.
+    pub subject: String,
+    pub logs: Vec<ErasedLog>,
+    /// Stored pseudonym mappings for the address that were deleted.
+    pub addr_mappings: u32,
+    /// Logs whose regression against an erased baseline was dropped.
+    pub regressions_cleared: Vec<u64>,
+    /// Logs marked as duplicates of an erased log, moved back to `New`.
+    #[serde(default)]
+    pub duplicates_cleared: Vec<u64>,
+    /// Audit log entries whose caller address was replaced by its pseudonym.
+    #[serde(default)]
+    pub audit_entries: u32,
+    /// Signatures drafted from an erased log, left for an admin to check, as their patterns may
+    /// quote it.
+    #[serde(default)]
+    pub signatures: Vec<u64>,
+    /// Logs that may or may not be the subject's, like ones stored under a truncated address
+    /// other addresses may share, left for an admin to check.
+    pub ambiguous: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct LogEntryPartial {
     pub id: u64,
     pub last_updated: u64,
-    pub addr: std::net::SocketAddr,
+    pub addr: Submitter,
     pub is_saved: bool,
+    pub meta: LogMeta,
+    pub status: Status,
+    pub assignee: Option<String>,
+    pub archived: bool,
+    pub key: Option<BaselineKey>,
+    pub is_baseline: bool,
+    pub regression: Option<RegressionSummary>,
+    /// Lines each of the server's rules matched, for rules that matched any.
+    pub flags: Vec<FlagCount>,
+    /// IDs of the [`Signature`]s the log matches.
+    pub known_issues: Vec<u64>,
+    /// What the log printed about the hardware and OS, like `device_id` or `kernel`.
+    pub facts: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// How bad a log line looks, going by words like "error" and "warning" in it.
+#[must_use]
+pub fn severity(line: &str) -> Option<Severity> {
+    let line = line.to_ascii_lowercase();
+    if ["error", "fail", "panic"].iter().any(|v| line.contains(v)) {
+        Some(Severity::Error)
+    } else if line.contains("warn") {
+        Some(Severity::Warning)
+    } else {
+        None
+    }
+}
+
+/// How many lines of a log one of the server's rules matched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FlagCount {
+    pub label: String,
+    pub severity: Severity,
+    pub count: u32,
+}
+
+/// A line of a log one of the server's rules matched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Flag {
+    /// 1-based.
+    pub line: u32,
+    pub label: String,
+    pub severity: Severity,
+    /// Where to read about what the line means.
+    pub link: Option<String>,
+}
+
+/// Sensitive text the server replaced in a stored log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Redaction {
+    /// Byte offset of the replacement in the log's data.
+    pub offset: u64,
+    /// Length of the replacement, in bytes.
+    pub len: u32,
+    /// Name of the detector that found it.
+    pub detector: String,
+    /// What was replaced, unless the server is set not to keep it.
+    pub original: Option<String>,
+}
+
+/// Something the server redacted, or would redact, from a log, as admins see it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RedactionReport {
+    /// 1-based.
+    pub line: u32,
+    pub detector: String,
+    /// Whether the stored log has it replaced, rather than only exports and share links.
+    pub applied: bool,
+    pub original: Option<String>,
+}
+
+/// Lines of a log the server's rules matched, found as data arrives.
+#[derive(Debug, Clone, Default)]
+pub struct RuleHits {
+    /// `(rule, line)` in line order, where `rule` is the index of the rule that matched and
+    /// `line` is 1-based.
+    pub hits: Vec<(usize, u32)>,
+    /// Bytes of the log scanned so far. Only complete lines are scanned.
+    pub end: usize,
+    /// Lines scanned so far.
+    pub lines: u32,
+}
+
+/// How far the server has analyzed a log, so only lines added since are analyzed again.
+#[derive(Debug, Clone, Default)]
+pub struct Analysis {
+    /// Bytes of the log analyzed so far. Only complete lines count; the line after them is
+    /// analyzed again until it's complete.
+    pub end: usize,
+    /// Lines analyzed so far.
+    pub lines: u32,
+    /// The baseline the analyzed lines were compared against.
+    pub baseline: Option<u64>,
+    /// Normalized error and warning lines of `baseline`, as of when the log was first compared
+    /// against it.
+    pub known: Arc<HashSet<String>>,
+}
+
+/// Signature patterns a log has printed, found as data arrives like [`RuleHits`].
+#[derive(Debug, Clone, Default)]
+pub struct PatternHits {
+    /// Bytes of the log matched so far. Only complete lines count.
+    pub end: usize,
+    /// Which signatures the log was matched against, as counted by the server.
+    pub generation: u64,
+    /// `(signature, pattern)`, where `pattern` is the index of a pattern of the signature with
+    /// ID `signature`.
+    pub found: HashSet<(u64, usize)>,
+}
+
+/// A known problem, recognised by what a log prints and what its summary says.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Signature {
+    pub id: u64,
+    pub title: String,
+    /// What to do about it, shown with the logs that match.
+    pub resolution: String,
+    /// Regexes that each have to match somewhere in the log. `^` and `$` match at line ends.
+    pub patterns: Vec<String>,
+    /// A [`query::Query`] the log's summary has to match, like `tag:ventura`. Empty matches
+    /// every log.
+    pub conditions: String,
+    pub created_by: String,
+    /// Unix timestamp in microseconds.
+    pub created_at: u64,
+    /// Log the signature was drafted from, if it was.
+    #[serde(default)]
+    pub drafted_from: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct NewSignature {
+    pub title: String,
+    pub resolution: String,
+    pub patterns: Vec<String>,
+    #[serde(default)]
+    pub conditions: String,
+    #[serde(default)]
+    pub drafted_from: Option<u64>,
+}
+
+/// Hardware and OS a log was taken on, for matching it against a known-good baseline.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BaselineKey {
+    /// PCI device ID of the GPU, like `0x15d8`.
+    pub device_id: String,
+    pub os_version: String,
+}
+
+impl std::fmt::Display for BaselineKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} on {}", self.device_id, self.os_version)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Baseline {
+    pub id: u64,
+    pub key: BaselineKey,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RegressionLine {
+    /// 1-based.
+    pub number: u32,
+    pub severity: Severity,
+    pub text: String,
+}
+
+/// Error and warning lines of a log that its baseline doesn't have.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Regression {
+    pub baseline: u64,
+    pub lines: Vec<RegressionLine>,
+}
+
+/// What a [`Regression`] amounts to, for listings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RegressionSummary {
+    pub baseline: u64,
+    pub errors: u32,
+    pub warnings: u32,
+}
+
+impl Regression {
+    #[must_use]
+    pub fn summary(&self) -> RegressionSummary {
+        let count = |severity| {
+            let n = self.lines.iter().filter(|v| v.severity == severity).count();
+            u32::try_from(n).unwrap_or(u32::MAX)
+        };
+        RegressionSummary {
+            baseline: self.baseline,
+            errors: count(Severity::Error),
+            warnings: count(Severity::Warning),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum DiffTag {
+    Equal,
+    /// Only in the old log.
+    Delete,
+    /// Only in the new log.
+    Insert,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct NumberedLine {
+    /// 1-based.
+    pub number: u32,
+    pub text: String,
+}
+
+/// A line of a [`LogDiff`], as it reads in each log it's in. Lines compared equal after
+/// normalizing can still read differently.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DiffLine {
+    pub tag: DiffTag,
+    pub old: Option<NumberedLine>,
+    pub new: Option<NumberedLine>,
+}
+
+/// Line diff between two logs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LogDiff {
+    /// Unchanged lines far from any change may be left out, leaving gaps in the line numbers.
+    pub lines: Vec<DiffLine>,
+    /// Index into `lines` of the first changed line, if the logs differ.
+    pub first_change: Option<usize>,
+}
+
+/// When the logger sent the data starting at `offset`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Received {
+    pub offset: u64,
+    /// Unix timestamp in microseconds.
+    pub at: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct LogEntry {
     pub last_updated: u64,
+    #[cfg_attr(feature = "openapi", schema(value_type = String))]
     pub addr: std::net::SocketAddr,
     pub data: String,
+    pub meta: LogMeta,
+    pub triage: Triage,
+    pub comments: Vec<Comment>,
+    /// Left out of listings unless asked for, without being deleted.
+    pub archived: bool,
+    /// One entry per chunk of `data` received, oldest first. Empty for logs saved before this
+    /// was recorded.
+    pub received: Vec<Received>,
+    /// Detected from the log once it has printed enough, unless it was set by hand.
+    pub key: Option<BaselineKey>,
+    /// Known-good log that sessions with the same key are compared against.
+    pub is_baseline: bool,
+    pub regression: Option<Regression>,
+    /// IDs of the [`Signature`]s the log matched when it last changed.
+    pub known_issues: Vec<u64>,
+    /// Extracted as data arrives, by the server's configured extractors.
+    pub facts: BTreeMap<String, String>,
+    /// Replacements made in `data`, in order.
+    pub redactions: Vec<Redaction>,
+    /// Bytes of `data` the server has redacted so far.
+    pub redacted: u64,
+    /// ID the next comment on the log gets, so IDs of deleted comments aren't reused.
+    pub next_comment_id: u64,
+    /// Filled in as lines are asked for, and never stored or sent.
+    #[serde(skip)]
+    #[cfg_attr(feature = "openapi", schema(ignore))]
+    pub parsed: parse::ParsedLog,
+    /// Found again when the server starts, so they follow its current rules.
+    #[serde(skip)]
+    #[cfg_attr(feature = "openapi", schema(ignore))]
+    pub hits: RuleHits,
+    /// Started over when the server starts.
+    #[serde(skip)]
+    #[cfg_attr(feature = "openapi", schema(ignore))]
+    pub analysis: Analysis,
+    /// Found again when the server starts, or the signatures change.
+    #[serde(skip)]
+    #[cfg_attr(feature = "openapi", schema(ignore))]
+    pub pattern_hits: PatternHits,
+}
+
+impl LogEntry {
+    /// A new session, with nothing known about it beyond its first data.
+    #[must_use]
+    pub fn new(last_updated: u64, addr: std::net::SocketAddr, data: String) -> Self {
+        Self {
+            last_updated,
+            addr,
+            data,
+            meta: LogMeta::default(),
+            triage: Triage::default(),
+            comments: Vec::new(),
+            archived: false,
+            received: Vec::new(),
+            key: None,
+            is_baseline: false,
+            regression: None,
+            known_issues: Vec::new(),
+            facts: BTreeMap::new(),
+            redactions: Vec::new(),
+            redacted: 0,
+            next_comment_id: 0,
+            parsed: parse::ParsedLog::default(),
+            hits: RuleHits::default(),
+            analysis: Analysis::default(),
+            pattern_hits: PatternHits::default(),
+        }
+    }
+}
+
+/// Pushed to live-tail subscribers as the log receiver and API mutate logs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+// Events are sent once and dropped, so a summary isn't worth boxing.
+#[allow(clippy::large_enum_variant)]
+pub enum LogEvent {
+    /// A session was created or its summary changed.
+    Session(LogEntryPartial),
+    /// `data` was appended to a session whose log was `offset` bytes long.
+    Append {
+        id: u64,
+        offset: u64,
+        last_updated: u64,
+        data: String,
+    },
+    Removed(u64),
+    /// A comment on the log with this ID was added, edited or removed.
+    Comments(u64),
+    /// The subscriber fell behind and missed events; it should refetch.
+    Lagged,
+}
+
+/// Version of the `/api/v{n}` namespace this crate describes.
+pub const API_VERSION: u32 = 1;
+
+/// Optional server features a client can check for in [`ServerInfo::capabilities`].
+pub mod capability {
+    pub const RANGES: &str = "ranges";
+    pub const STREAM: &str = "stream";
+    pub const JSON: &str = "json";
+    pub const OPENAPI: &str = "openapi";
+    pub const SHARE: &str = "share";
+    pub const SESSION_CODES: &str = "session-codes";
+    pub const META: &str = "meta";
+    pub const QUERY: &str = "query";
+    pub const TRIAGE: &str = "triage";
+    pub const COMMENTS: &str = "comments";
+    pub const BULK: &str = "bulk";
+    pub const ARCHIVE: &str = "archive";
+    pub const DOWNLOAD: &str = "download";
+    pub const DIFF: &str = "diff";
+    pub const BASELINES: &str = "baselines";
+    pub const PARSED_LINES: &str = "parsed-lines";
+    pub const FLAGS: &str = "flags";
+    pub const SIGNATURES: &str = "signatures";
+    pub const FACTS: &str = "facts";
+    pub const REDACTION: &str = "redaction";
+    pub const ADDR_MAP: &str = "addr-map";
+    pub const ERASE: &str = "erase";
+    pub const AUDIT: &str = "audit";
+}
+
+/// Crockford's base32 alphabet, which leaves out I, L, O and U.
+const CODE_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const CODE_PREFIX: &str = "RED-";
+const CODE_LENGTH: usize = 5;
+
+/// Short code for a session, like `RED-7K3QX`, that's easier to read out than its ID. Codes are
+/// derived from the ID, so they aren't guaranteed to be unique.
+#[must_use]
+pub fn session_code(id: u64) -> String {
+    // SplitMix64's finaliser, so sessions created back to back get unrelated codes.
+    let mut v = id;
+    v = (v ^ (v >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    v = (v ^ (v >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    v ^= v >> 31;
+
+    let mut s = String::from(CODE_PREFIX);
+    for i in (0..CODE_LENGTH).rev() {
+        #[allow(clippy::cast_possible_truncation)]
+        s.push(CODE_ALPHABET[(v >> (i * 5)) as usize & 31] as char);
+    }
+    s
+}
+
+/// Normalises a code as a person might type it: any case, with or without the prefix, and
+/// with the letters Crockford's base32 leaves out read as the digits they look like.
+#[must_use]
+pub fn parse_session_code(code: &str) -> Option<String> {
+    let code = code.trim().to_ascii_uppercase();
+    let code = code.strip_prefix(CODE_PREFIX).unwrap_or(&code);
+    let code: String = code
+        .chars()
+        .filter(|&v| v != '-')
+        .map(|v| match v {
+            'I' | 'L' => '1',
+            'O' => '0',
+            v => v,
+        })
+        .collect();
+    (code.len() == CODE_LENGTH && code.bytes().all(|v| CODE_ALPHABET.contains(&v)))
+        .then(|| format!("{CODE_PREFIX}{code}"))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ServerInfo {
+    pub version: String,
+    pub api_version: u32,
+    pub capabilities: Vec<String>,
+}
+
+impl ServerInfo {
+    #[must_use]
+    pub fn supports(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|v| v == capability)
+    }
+}
+
+/// 1-based, inclusive range of lines. Written as `first-last` in share links.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LineRange {
+    pub first: u32,
+    pub last: u32,
+}
+
+impl std::fmt::Display for LineRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.first, self.last)
+    }
+}
+
+impl std::str::FromStr for LineRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (first, last) = s.split_once('-').ok_or("Expected `first-last`")?;
+        let v = Self {
+            first: first.parse().map_err(|e| format!("{e}"))?,
+            last: last.parse().map_err(|e| format!("{e}"))?,
+        };
+        if v.first == 0 || v.first > v.last {
+            return Err("Lines are 1-based and `first` can't be after `last`".to_owned());
+        }
+        Ok(v)
+    }
+}
+
+/// Asks for a link that opens a log without a token until it expires.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ShareRequest {
+    /// Seconds until the link stops working.
+    pub expires_in: u64,
+    /// Only show these lines.
+    pub lines: Option<LineRange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ShareLink {
+    /// Path and query of the link, relative to the server's base URL.
+    pub path: String,
+    /// Unix timestamp in seconds.
+    pub expires: u64,
 }
 
 #[must_use]
 pub fn get_id_props() -> sequence_generator::SequenceProperties {
     sequence_generator::SequenceProperties::new(std::time::UNIX_EPOCH, 10, 500, 12, 3, 1, 1500)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_codes_round_trip() {
+        for id in [0, 1, 2, 12345, u64::MAX] {
+            let code = session_code(id);
+            assert!(code.starts_with(CODE_PREFIX));
+            assert_eq!(parse_session_code(&code), Some(code.clone()));
+            let typed = code[CODE_PREFIX.len()..].to_lowercase();
+            assert_eq!(parse_session_code(&format!(" {typed} ")), Some(code));
+        }
+        assert_ne!(session_code(1), session_code(2));
+    }
+
+    #[test]
+    fn session_codes_read_lookalikes() {
+        let code = format!("{CODE_PREFIX}{}", "1".repeat(CODE_LENGTH));
+        let typed = "l".repeat(CODE_LENGTH - 1) + "I";
+        assert_eq!(parse_session_code(&typed), Some(code));
+        assert_eq!(parse_session_code(""), None);
+        assert_eq!(parse_session_code("RED-U0000"), None);
+        assert_eq!(parse_session_code(&"0".repeat(CODE_LENGTH + 1)), None);
+    }
+}
@@ -1,5 +1,11 @@
 use actix_web::web;
 use sequence_generator::sequence_generator;
+use tokio::io::AsyncWriteExt;
+
+use crate::{redact::Stage, state::AppState};
+
+/// Longest partial line held back for redaction before it's stored anyway.
+const MAX_PENDING: usize = 64 * 1024;
 
 fn generate_id() -> (sequence_generator::SequenceProperties, u64) {
     let properties = wred_server::get_id_props();
@@ -7,17 +13,77 @@ fn generate_id() -> (sequence_generator::SequenceProperties, u64) {
     (properties, id)
 }
 
+/// Adds `text` from `addr` to its session, or starts one. Returns the session's ID.
+fn receive(state: &AppState, addr: std::net::SocketAddr, text: String) -> u64 {
+    let (properties, id) = generate_id();
+    let mut v = wred_server::LogEntry::new(
+        sequence_generator::decode_id_unix_epoch_micros(id, &properties),
+        addr,
+        text,
+    );
+    let ingest = state.redactors.at() == Stage::Ingest;
+
+    let mut logs = state.logs.lock().unwrap();
+    if let Some((&id, ent)) = logs
+        .iter_mut()
+        .find(|(_, e)| e.addr.ip() == addr.ip() && v.last_updated - e.last_updated < 60_000_000)
+    {
+        let offset = ent.data.len();
+        ent.last_updated = v.last_updated;
+        ent.received.push(wred_server::Received {
+            offset: offset as u64,
+            at: v.last_updated,
+        });
+        ent.data += &v.data;
+        let mut changed = ingest && state.redact_log(ent, true);
+        state.notify(wred_server::LogEvent::Append {
+            id,
+            offset: offset as u64,
+            last_updated: v.last_updated,
+            data: ent.data[offset..].to_owned(),
+        });
+        changed |= state.rules.scan(ent);
+        if changed {
+            state.notify(wred_server::LogEvent::Session(state.partial(id, ent)));
+        }
+        id
+    } else {
+        v.received.push(wred_server::Received {
+            offset: 0,
+            at: v.last_updated,
+        });
+        if ingest {
+            state.redact_log(&mut v, true);
+        }
+        state.rules.scan(&mut v);
+        state.notify(wred_server::LogEvent::Session(state.partial(id, &v)));
+        logs.insert(id, v);
+        drop(logs);
+        id
+    }
+}
+
 pub async fn start_log_receiver(state: web::Data<crate::state::AppState>) {
     let bind = (state.config.ip.clone(), state.config.logger_port);
     let listener = tokio::net::TcpListener::bind(bind).await.unwrap();
 
     tokio::spawn(async move {
         loop {
-            let (stream, addr) = listener.accept().await.unwrap();
-            let log_entries = state.logs.clone();
+            let (mut stream, addr) = listener.accept().await.unwrap();
+            let state = state.clone();
 
             tokio::spawn(async move {
+                // Taking the log without being able to tell whose it is would defeat erasure.
+                let addr = match state.store_addr(addr).await {
+                    Ok(v) => v,
+                    Err(e) => {
+                        eprintln!("Refused log from {addr}, failed saving address map: {e}");
+                        return;
+                    }
+                };
                 let mut buf = Vec::new();
+                // Session the submitter was last told about, if `logger_reply` is on.
+                let mut replied_for = None;
                 let e: std::io::Result<()> = loop {
                     tokio::time::timeout(std::time::Duration::from_secs(15), stream.readable())
                         .await
@@ -27,36 +93,46 @@ pub async fn start_log_receiver(state: web::Data<crate::state::AppState>) {
                     match stream.try_read_buf(&mut buf) {
                         Ok(0) => break Ok(()),
                         Ok(_) => {
-                            let (properties, id) = generate_id();
-                            let v = wred_server::LogEntry {
-                                last_updated: sequence_generator::decode_id_unix_epoch_micros(
-                                    id,
-                                    &properties,
-                                ),
-                                addr,
-                                data: String::from_utf8_lossy(&buf).to_string(),
+                            // Lines are only redacted whole, so hold back a partial last line
+                            // until the rest of it arrives.
+                            let end = if state.redactors.at() == Stage::Ingest {
+                                match buf.iter().rposition(|&v| v == b'\n') {
+                                    Some(n) => n + 1,
+                                    None if buf.len() >= MAX_PENDING => buf.len(),
+                                    None => continue,
+                                }
+                            } else {
+                                buf.len()
                             };
-                            buf.clear();
+                            let text = String::from_utf8_lossy(&buf[..end]).to_string();
+                            buf.drain(..end);
+                            let session = receive(&state, addr, text);
 
-                            let mut logs = log_entries.lock().unwrap();
-                            if let Some(ent) = logs.values_mut().find(|e| {
-                                e.addr.ip() == addr.ip()
-                                    && v.last_updated - e.last_updated < 60_000_000
-                            }) {
-                                ent.last_updated = v.last_updated;
-                                ent.data += &v.data;
-                            } else {
-                                logs.insert(id, v);
+                            if state.config.logger_reply && replied_for != Some(session) {
+                                replied_for = Some(session);
+                                let reply = format!(
+                                    "WRED-SESSION {}\nWRED-CLAIM {}\n",
+                                    wred_server::session_code(session),
+                                    crate::signing::claim_token(state.signing_key(), session)
+                                );
+                                // The submitter may well not be listening.
+                                let _e = stream.write_all(reply.as_bytes()).await;
                             }
                         }
-                        Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                            continue;
+                        Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+                        // Submitters that don't read the logger reply may reset the connection
+                        // when closing it with the reply unread.
+                        Err(ref e) if e.kind() == std::io::ErrorKind::ConnectionReset => {
+                            break Ok(());
                         }
                         Err(e) => {
                             break Err(e);
                         }
-                    };
+                    }
                 };
+                if !buf.is_empty() {
+                    receive(&state, addr, String::from_utf8_lossy(&buf).to_string());
+                }
                 e.unwrap();
             });
         }
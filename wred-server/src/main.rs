@@ -8,12 +8,32 @@
 )]
 #![allow(clippy::module_name_repetitions)]
 
-use actix_web::{web, App, HttpServer};
+use actix_web::{
+    dev::Service,
+    http::header::{HeaderName, HeaderValue},
+    web, App, HttpServer,
+};
 use tokio::io::AsyncReadExt;
 
+mod addrs;
+mod analyzer;
+mod audit;
+mod auth;
+mod codec;
+mod diff;
+mod erase;
+mod export;
+mod facts;
+mod legacy;
 mod log_service;
+mod openapi;
+mod redact;
 mod routes;
+mod rules;
+mod signatures;
+mod signing;
 mod state;
+mod storage;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -23,37 +43,135 @@ async fn main() -> std::io::Result<()> {
     let mut s = String::new();
     f.read_to_string(&mut s).await.unwrap();
     let config: state::ServerConfig = ron::de::from_str(&s).unwrap();
-    let state = web::Data::new(state::AppState {
-        config,
-        ..Default::default()
-    });
-    let _e = tokio::fs::create_dir_all(&state.config.log_dir).await;
+    let extra_tokens = match &config.token_file {
+        Some(path) => auth::load_token_file(path)
+            .await
+            .expect("Failed loading token file"),
+        None => Vec::new(),
+    };
+    let rules = match &config.rule_file {
+        Some(path) => rules::load_rule_file(path)
+            .await
+            .expect("Failed loading rule file"),
+        None => rules::default_rules(),
+    };
+    let _e = tokio::fs::create_dir_all(&config.log_dir).await;
+    let key = signing::load_key(&config)
+        .await
+        .expect("Failed loading signing key");
+    let state = web::Data::new(state::AppState::new(config, extra_tokens, rules, key));
     let mut rd = tokio::fs::read_dir(&state.config.log_dir).await.unwrap();
     loop {
         match rd.next_entry().await.unwrap() {
             None => break,
             Some(ent) => {
                 let path = ent.path();
-                if path.is_file() {
+                if path.is_file() && path.extension().is_some_and(|v| v == "log") {
                     let data = tokio::fs::read(path).await.unwrap();
-                    let ent: (u64, wred_server::LogEntry) = postcard::from_bytes(&data).unwrap();
-                    state.logs.lock().unwrap().insert(ent.0, ent.1);
+                    let (id, mut ent) = storage::decode_log(&data).unwrap();
+                    state.rules.scan(&mut ent);
+                    state.logs.lock().unwrap().insert(id, ent);
                 }
             }
         }
     }
+    state.open_audit().await.expect("Failed opening audit log");
+    state
+        .load_share_generation()
+        .await
+        .expect("Failed loading share generation");
+    state
+        .load_signatures()
+        .await
+        .expect("Failed loading signatures");
+    state
+        .load_addr_map()
+        .await
+        .expect("Failed loading address map");
+    state
+        .migrate_addrs()
+        .await
+        .expect("Failed storing pseudonyms in saved logs");
 
     let bind = (state.config.ip.clone(), state.config.api_port);
     log_service::start_log_receiver(state.clone()).await;
+    analyzer::start(state.clone());
+    addrs::start(state.clone());
 
     HttpServer::new(move || {
         App::new()
             .app_data(state.clone())
-            .service(routes::get_logs)
-            .service(routes::get_log)
-            .service(routes::delete_log)
-            .service(routes::save_log)
-            .service(actix_files::Files::new("/", "./dist").index_file("index.html"))
+            .wrap(audit::Audit)
+            .service(
+                web::scope(&format!("/api/v{}", wred_server::API_VERSION))
+                    .service(routes::get_version)
+                    .service(routes::get_logs)
+                    .service(routes::stream_logs)
+                    .service(routes::get_log)
+                    .service(routes::download_log)
+                    .service(routes::get_lines)
+                    .service(routes::get_flags)
+                    .service(routes::get_signatures)
+                    .service(routes::add_signature)
+                    .service(routes::delete_signature)
+                    .service(routes::draft_signature)
+                    .service(routes::get_redactions)
+                    .service(routes::get_addr_map)
+                    .service(routes::erase_subject)
+                    .service(routes::get_erasures)
+                    .service(routes::get_audit)
+                    .service(routes::diff_logs)
+                    .service(routes::get_baselines)
+                    .service(routes::set_baseline)
+                    .service(routes::unset_baseline)
+                    .service(routes::get_regression)
+                    .service(routes::delete_log)
+                    .service(routes::save_log)
+                    .service(routes::unsave_log)
+                    .service(routes::archive_log)
+                    .service(routes::unarchive_log)
+                    .service(routes::patch_log)
+                    .service(routes::set_status)
+                    .service(routes::set_assignee)
+                    .service(routes::get_history)
+                    .service(routes::get_comments)
+                    .service(routes::add_comment)
+                    .service(routes::edit_comment)
+                    .service(routes::delete_comment)
+                    .service(routes::bulk_save)
+                    .service(routes::bulk_delete)
+                    .service(routes::bulk_tag)
+                    .service(routes::bulk_status)
+                    .service(routes::get_claim_token)
+                    .service(routes::share_log)
+                    .service(routes::get_shared_log)
+                    .service(routes::rotate_share_links)
+                    .service(routes::resolve_session_code)
+                    .service(openapi::get_openapi),
+            )
+            // Pre-versioning paths, kept until old clients are gone.
+            .service(
+                web::scope("")
+                    .wrap_fn(|req, srv| {
+                        let fut = srv.call(req);
+                        async move {
+                            let mut res = fut.await?;
+                            // Static files fall through to here too, only flag the API routes.
+                            if res.request().match_pattern().is_some() {
+                                res.headers_mut().insert(
+                                    HeaderName::from_static("deprecation"),
+                                    HeaderValue::from_static("true"),
+                                );
+                            }
+                            Ok(res)
+                        }
+                    })
+                    .service(legacy::get_logs)
+                    .service(routes::get_log)
+                    .service(legacy::delete_log)
+                    .service(legacy::save_log),
+            )
+            .default_service(actix_files::Files::new("/", "./dist").index_file("index.html"))
     })
     .bind(bind)?
     .run()
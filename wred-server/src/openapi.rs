@@ -0,0 +1,79 @@
+use actix_web::{get, HttpResponse, Responder};
+use utoipa::{
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi,
+};
+
+/// Bodies are postcard unless the request asks for `application/json` through `Accept` or
+/// `Content-Type`. The unversioned routes at the root are deprecated aliases and left out.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "WhateverRed NETDBG"),
+    modifiers(&BearerToken),
+    paths(
+        crate::routes::get_version,
+        crate::routes::get_logs,
+        crate::routes::stream_logs,
+        crate::routes::get_log,
+        crate::routes::download_log,
+        crate::routes::get_lines,
+        crate::routes::get_flags,
+        crate::routes::get_signatures,
+        crate::routes::add_signature,
+        crate::routes::delete_signature,
+        crate::routes::draft_signature,
+        crate::routes::get_redactions,
+        crate::routes::get_addr_map,
+        crate::routes::erase_subject,
+        crate::routes::get_erasures,
+        crate::routes::get_audit,
+        crate::routes::diff_logs,
+        crate::routes::get_baselines,
+        crate::routes::set_baseline,
+        crate::routes::unset_baseline,
+        crate::routes::get_regression,
+        crate::routes::delete_log,
+        crate::routes::save_log,
+        crate::routes::unsave_log,
+        crate::routes::archive_log,
+        crate::routes::unarchive_log,
+        crate::routes::patch_log,
+        crate::routes::set_status,
+        crate::routes::set_assignee,
+        crate::routes::get_history,
+        crate::routes::get_comments,
+        crate::routes::add_comment,
+        crate::routes::edit_comment,
+        crate::routes::delete_comment,
+        crate::routes::bulk_save,
+        crate::routes::bulk_delete,
+        crate::routes::bulk_tag,
+        crate::routes::bulk_status,
+        crate::routes::get_claim_token,
+        crate::routes::share_log,
+        crate::routes::get_shared_log,
+        crate::routes::rotate_share_links,
+        crate::routes::resolve_session_code,
+    )
+)]
+pub struct ApiDoc;
+
+struct BearerToken;
+
+impl Modify for BearerToken {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        openapi
+            .components
+            .get_or_insert_with(Default::default)
+            .add_security_scheme(
+                "token",
+                SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+            );
+    }
+}
+
+#[get("/openapi.json")]
+#[allow(clippy::unused_async)]
+async fn get_openapi() -> impl Responder {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
@@ -0,0 +1,282 @@
+//! Splits log lines into their parts, like `WhateverRed:        rad: wrapInitHW: done` into
+//! the product, subsystem `rad`, function `wrapInitHW` and message `done`.
+//!
+//! A line may start with a prefix the subsystem doesn't print: a `[  12.345678]` uptime, or a
+//! `log show` timestamp up to `kernel: ` and the `(kext)` that logged it. Next is either a
+//! `[module]` tag or up to three `name: ` segments. With three, the first is the product, as
+//! Lilu plugins print it, and is kept in the prefix; the others are the subsystem and function.
+
+use serde::{Deserialize, Serialize};
+
+use crate::Severity;
+
+/// Longest segment taken for a subsystem or function name.
+const MAX_NAME_LENGTH: usize = 48;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ParsedLine {
+    /// 1-based.
+    pub number: u32,
+    /// Whatever comes before the subsystem, like a timestamp. Empty if there's nothing.
+    pub prefix: String,
+    pub subsystem: Option<String>,
+    pub function: Option<String>,
+    pub severity: Option<Severity>,
+    pub message: String,
+}
+
+fn is_name(s: &str) -> bool {
+    !s.is_empty()
+        && s.len() <= MAX_NAME_LENGTH
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | ':' | '~'))
+}
+
+/// Length of a leading `[  12.345678] ` uptime, if there is one.
+fn uptime_len(line: &str) -> Option<usize> {
+    let inner = line.strip_prefix('[')?;
+    let end = inner.find(']')?;
+    let uptime = inner[..end].trim();
+    if uptime.is_empty() || !uptime.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+    let rest = &inner[end + 1..];
+    Some(line.len() - rest.trim_start().len())
+}
+
+/// Length of a leading `... kernel: (kext) ` from `log show`, if there is one. `kernel` has to
+/// be the process name ending the prefix, not a word in the message.
+fn kernel_len(line: &str) -> Option<usize> {
+    let end = line.find(": ")?;
+    let head = &line[..end];
+    let before = head
+        .strip_suffix("kernel")
+        .or_else(|| head.strip_suffix("kernel[0]"))?;
+    if before.contains(']') || !(before.is_empty() || before.ends_with(char::is_whitespace)) {
+        return None;
+    }
+    let mut len = end + 2;
+    if let Some(sender) = line[len..].strip_prefix('(') {
+        if let Some(end) = sender.find(") ") {
+            len += end + 3;
+        }
+    }
+    Some(len)
+}
+
+/// Splits `name: rest` where `name` looks like an identifier, ignoring padding before it.
+fn split_name(s: &str) -> Option<(&str, &str)> {
+    let trimmed = s.trim_start();
+    let (name, rest) = trimmed.split_once(": ")?;
+    is_name(name).then_some((name, rest))
+}
+
+/// Splits one line, without its line break.
+#[must_use]
+pub fn parse_line(number: u32, line: &str) -> ParsedLine {
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    let prefix_len = uptime_len(line).or_else(|| kernel_len(line)).unwrap_or(0);
+    let (mut prefix, rest) = line.split_at(prefix_len);
+    let severity = crate::severity(rest);
+
+    if let Some((module, message)) = rest.strip_prefix('[').and_then(|v| v.split_once(']')) {
+        if is_name(module) {
+            return ParsedLine {
+                number,
+                prefix: prefix.trim_end().to_owned(),
+                subsystem: Some(module.to_owned()),
+                function: None,
+                severity,
+                message: message.trim_start().to_owned(),
+            };
+        }
+    }
+
+    let mut names = Vec::with_capacity(3);
+    let mut message = rest;
+    while names.len() < 3 {
+        let Some((name, tail)) = split_name(message) else {
+            break;
+        };
+        // `AMDRadeonX6000: Error: ...` has no function.
+        if !names.is_empty() && crate::severity(name).is_some() {
+            break;
+        }
+        names.push(name);
+        message = tail;
+    }
+    // The product a Lilu plugin prints first belongs with the prefix.
+    if names.len() == 3 {
+        let padding = rest.len() - rest.trim_start().len();
+        prefix = &line[..=prefix_len + padding + names[0].len()];
+        names.remove(0);
+    }
+    let mut names = names.into_iter().map(ToOwned::to_owned);
+    ParsedLine {
+        number,
+        prefix: prefix.trim_end().to_owned(),
+        subsystem: names.next(),
+        function: names.next(),
+        severity,
+        message: message.trim_start().to_owned(),
+    }
+}
+
+/// Parsed lines of a log, kept alongside it so they're only parsed once. Only complete lines
+/// are kept, as the last one may still be growing.
+#[derive(Debug, Clone, Default)]
+pub struct ParsedLog {
+    lines: Vec<ParsedLine>,
+    /// Bytes of the log parsed so far.
+    end: usize,
+}
+
+impl ParsedLog {
+    /// Parses the complete lines of `data` added since the last call. Starts over if `data` is
+    /// shorter than what was parsed, as it can't have only been appended to. Call
+    /// [`Self::clear`] if it was changed some other way.
+    pub fn update(&mut self, data: &str) {
+        if data.len() < self.end || !data.is_char_boundary(self.end) {
+            *self = Self::default();
+        }
+        let Some(last) = data[self.end..].rfind('\n') else {
+            return;
+        };
+        let new = &data[self.end..=self.end + last];
+        let mut number = u32::try_from(self.lines.len()).unwrap_or(u32::MAX);
+        self.lines.extend(new.lines().map(|line| {
+            number = number.saturating_add(1);
+            parse_line(number, line)
+        }));
+        self.end += last + 1;
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    #[must_use]
+    pub fn lines(&self) -> &[ParsedLine] {
+        &self.lines
+    }
+
+    /// The incomplete last line of `data`, which [`Self::update`] leaves out.
+    #[must_use]
+    pub fn rest(&self, data: &str) -> Option<ParsedLine> {
+        let rest = data.get(self.end..).filter(|v| !v.is_empty())?;
+        let number = u32::try_from(self.lines.len() + 1).unwrap_or(u32::MAX);
+        Some(parse_line(number, rest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts(line: &str) -> (String, Option<String>, Option<String>, String) {
+        let v = parse_line(1, line);
+        (v.prefix, v.subsystem, v.function, v.message)
+    }
+
+    fn owned(
+        prefix: &str,
+        subsystem: Option<&str>,
+        function: Option<&str>,
+        message: &str,
+    ) -> (String, Option<String>, Option<String>, String) {
+        (
+            prefix.to_owned(),
+            subsystem.map(ToOwned::to_owned),
+            function.map(ToOwned::to_owned),
+            message.to_owned(),
+        )
+    }
+
+    #[test]
+    fn splits_names() {
+        assert_eq!(
+            parts("WhateverRed:        rad: wrapInitHW: done"),
+            owned("WhateverRed:", Some("rad"), Some("wrapInitHW"), "done")
+        );
+        assert_eq!(
+            parts("rad: wrapInitHW: done\r"),
+            owned("", Some("rad"), Some("wrapInitHW"), "done")
+        );
+        assert_eq!(
+            parts("AMDRadeonX6000: Error: bad thing"),
+            owned("", Some("AMDRadeonX6000"), None, "Error: bad thing")
+        );
+        assert_eq!(
+            parts("just a message"),
+            owned("", None, None, "just a message")
+        );
+    }
+
+    #[test]
+    fn splits_prefixes() {
+        assert_eq!(
+            parts("[  12.345678] rad: init: ok"),
+            owned("[  12.345678]", Some("rad"), Some("init"), "ok")
+        );
+        assert_eq!(
+            parts("2023-01-01 00:00:00.1 0x1 Default 0x0 0 0 kernel: (WhateverRed) rad: init: ok"),
+            owned(
+                "2023-01-01 00:00:00.1 0x1 Default 0x0 0 0 kernel: (WhateverRed)",
+                Some("rad"),
+                Some("init"),
+                "ok"
+            )
+        );
+        assert_eq!(
+            parts("[MyKext] starting up"),
+            owned("", Some("MyKext"), None, "starting up")
+        );
+        assert_eq!(
+            parts("[  1.5] [MyKext] starting up"),
+            owned("[  1.5]", Some("MyKext"), None, "starting up")
+        );
+    }
+
+    #[test]
+    fn leaves_kernel_in_messages() {
+        assert_eq!(
+            parts("mykernel: ok"),
+            owned("", Some("mykernel"), None, "ok")
+        );
+        assert_eq!(
+            parts("[MyKext] panic in kernel: oops"),
+            owned("", Some("MyKext"), None, "panic in kernel: oops")
+        );
+        assert_eq!(
+            parts("rad: kernel: ok"),
+            owned("", Some("rad"), Some("kernel"), "ok")
+        );
+    }
+
+    #[test]
+    fn picks_severity() {
+        assert_eq!(
+            parse_line(1, "rad: init: failed").severity,
+            Some(Severity::Error)
+        );
+        assert_eq!(
+            parse_line(1, "rad: init: warning").severity,
+            Some(Severity::Warning)
+        );
+        assert_eq!(parse_line(1, "rad: init: ok").severity, None);
+    }
+
+    #[test]
+    fn parses_complete_lines() {
+        let mut log = ParsedLog::default();
+        log.update("a: b\nc: ");
+        assert_eq!(log.lines().len(), 1);
+        assert_eq!(log.rest("a: b\nc: ").unwrap().number, 2);
+        log.update("a: b\nc: d\n");
+        assert_eq!(log.lines()[1].subsystem.as_deref(), Some("c"));
+        assert!(log.rest("a: b\nc: d\n").is_none());
+        log.update("x\n");
+        assert_eq!(log.lines().len(), 1);
+    }
+}
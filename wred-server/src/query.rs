@@ -0,0 +1,290 @@
+//! Filters over log summaries, like `tag:ventura -saved:yes "black screen"`.
+//!
+//! A query is a list of terms that all have to match. A term is either bare text, matched
+//! against the title, notes, tags and session code, or `field:value` for one field. Prefixing
+//! a term with `-` negates it, and double quotes keep spaces in a value. Text is matched
+//! case-insensitively.
+//!
+//! The fields are `title`, `notes`, `tag`, `addr`, `code`, `saved` (`yes` or `no`), `status`
+//! (`new`, `triaging`, `needs-info`, `resolved` or `duplicate-of`), `assignee`, `archived`
+//! (`yes` or `no`), `flag`, the label of a rule that flagged a line, and `fact.<name>` for a
+//! fact the server extracted, like `fact.device_id:15d8`. A term whose prefix isn't one of
+//! these, like `error:timeout`, is bare text. Archived logs only match queries with an
+//! `archived` term.
+
+use crate::LogEntryPartial;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Field {
+    Any,
+    Title,
+    Notes,
+    /// Matches tags exactly, rather than as a substring.
+    Tag,
+    Addr,
+    Code,
+    /// `yes` or `no`.
+    Saved,
+    /// A [`crate::Status::name`].
+    Status,
+    /// Matches assignees exactly. `assignee:` alone matches unassigned logs.
+    Assignee,
+    /// `yes` or `no`.
+    Archived,
+    /// Matches the labels of rules that flagged lines of the log exactly.
+    Flag,
+    /// `fact.<name>`, like `fact.device_id`. `fact.<name>:` alone matches logs without the fact.
+    Fact(String),
+}
+
+impl std::str::FromStr for Field {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_ascii_lowercase().as_str() {
+            "title" => Self::Title,
+            "notes" => Self::Notes,
+            "tag" => Self::Tag,
+            "addr" => Self::Addr,
+            "code" => Self::Code,
+            "saved" => Self::Saved,
+            "status" => Self::Status,
+            "assignee" => Self::Assignee,
+            "archived" => Self::Archived,
+            "flag" => Self::Flag,
+            v if v.starts_with("fact.") && v.len() > "fact.".len() => {
+                Self::Fact(v["fact.".len()..].to_owned())
+            }
+            _ => return Err(format!("Unknown field {s:?}")),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Term {
+    pub field: Field,
+    pub value: String,
+    pub negated: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+    pub terms: Vec<Term>,
+}
+
+/// Splits on whitespace outside double quotes, dropping the quotes.
+fn tokenize(s: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut cur = String::new();
+    let mut in_quotes = false;
+    let mut has_token = false;
+    for c in s.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_token = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if has_token {
+                    tokens.push(std::mem::take(&mut cur));
+                    has_token = false;
+                }
+            }
+            c => {
+                cur.push(c);
+                has_token = true;
+            }
+        }
+    }
+    if in_quotes {
+        return Err("Unterminated quote".to_owned());
+    }
+    if has_token {
+        tokens.push(cur);
+    }
+    Ok(tokens)
+}
+
+impl std::str::FromStr for Query {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let terms = tokenize(s)?
+            .into_iter()
+            .map(|token| {
+                let (negated, token) = token
+                    .strip_prefix('-')
+                    .map_or((false, token.as_str()), |v| (true, v));
+                let (field, value) = token
+                    .split_once(':')
+                    .and_then(|(field, value)| Some((field.parse().ok()?, value)))
+                    .unwrap_or((Field::Any, token));
+                let value = value.to_lowercase();
+                if matches!(field, Field::Saved | Field::Archived)
+                    && !matches!(value.as_str(), "yes" | "no")
+                {
+                    let field = token.split_once(':').map_or("", |v| v.0);
+                    return Err(format!(
+                        "Expected `{field}:yes` or `{field}:no`, got {value:?}"
+                    ));
+                }
+                Ok(Term {
+                    field,
+                    value,
+                    negated,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self { terms })
+    }
+}
+
+impl Term {
+    fn matches(&self, ent: &LogEntryPartial) -> bool {
+        let contains = |v: &str| v.to_lowercase().contains(&self.value);
+        let matched = match &self.field {
+            Field::Any => {
+                contains(&ent.meta.title)
+                    || contains(&ent.meta.notes)
+                    || ent.meta.tags.iter().any(|v| contains(v))
+                    || contains(&crate::session_code(ent.id))
+            }
+            Field::Title => contains(&ent.meta.title),
+            Field::Notes => contains(&ent.meta.notes),
+            Field::Tag => ent.meta.tags.iter().any(|v| v.to_lowercase() == self.value),
+            Field::Addr => contains(&ent.addr.to_string()),
+            Field::Code => {
+                crate::parse_session_code(&self.value) == Some(crate::session_code(ent.id))
+            }
+            Field::Saved => ent.is_saved == (self.value == "yes"),
+            Field::Archived => ent.archived == (self.value == "yes"),
+            Field::Flag => ent
+                .flags
+                .iter()
+                .any(|v| v.label.to_lowercase() == self.value),
+            Field::Fact(name) => {
+                let fact = ent.facts.iter().find(|(k, _)| k.to_lowercase() == *name);
+                match fact {
+                    Some((_, v)) => !self.value.is_empty() && contains(v),
+                    None => self.value.is_empty(),
+                }
+            }
+            Field::Status => ent.status.name() == self.value,
+            Field::Assignee => {
+                ent.assignee.as_deref().unwrap_or_default().to_lowercase() == self.value
+            }
+        };
+        matched != self.negated
+    }
+}
+
+impl Query {
+    #[must_use]
+    pub fn matches(&self, ent: &LogEntryPartial) -> bool {
+        let wants_archived = self.terms.iter().any(|v| v.field == Field::Archived);
+        (wants_archived || !ent.archived) && self.terms.iter().all(|v| v.matches(ent))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FlagCount, LogMeta, Severity, Status, Submitter};
+
+    fn term(field: Field, value: &str, negated: bool) -> Term {
+        Term {
+            field,
+            value: value.to_owned(),
+            negated,
+        }
+    }
+
+    fn entry() -> LogEntryPartial {
+        LogEntryPartial {
+            id: 7,
+            last_updated: 0,
+            addr: Submitter::Addr("10.0.0.2:5000".parse().unwrap()),
+            is_saved: true,
+            meta: LogMeta {
+                title: "Black screen on wake".to_owned(),
+                notes: String::new(),
+                tags: ["Ventura".to_owned()].into(),
+            },
+            status: Status::NeedsInfo,
+            assignee: None,
+            archived: false,
+            key: None,
+            is_baseline: false,
+            regression: None,
+            flags: vec![FlagCount {
+                label: "GPU hang".to_owned(),
+                severity: Severity::Error,
+                count: 1,
+            }],
+            known_issues: Vec::new(),
+            facts: [("device_id".to_owned(), "0x15D8".to_owned())].into(),
+        }
+    }
+
+    fn matches(query: &str) -> bool {
+        query.parse::<Query>().unwrap().matches(&entry())
+    }
+
+    #[test]
+    fn parses_terms() {
+        let query = r#"tag:ventura -saved:YES "black screen" Fact.Device_ID: error:timeout"#
+            .parse::<Query>()
+            .unwrap();
+        assert_eq!(
+            query.terms,
+            [
+                term(Field::Tag, "ventura", false),
+                term(Field::Saved, "yes", true),
+                term(Field::Any, "black screen", false),
+                term(Field::Fact("device_id".to_owned()), "", false),
+                term(Field::Any, "error:timeout", false),
+            ]
+        );
+        assert_eq!("".parse::<Query>().unwrap(), Query::default());
+        assert_eq!(
+            r#"title:"a b""#.parse::<Query>().unwrap().terms,
+            [term(Field::Title, "a b", false)]
+        );
+    }
+
+    #[test]
+    fn rejects_bad_queries() {
+        assert!(r#"title:"black"#.parse::<Query>().is_err());
+        assert!("saved:maybe".parse::<Query>().is_err());
+        assert!("archived:".parse::<Query>().is_err());
+    }
+
+    #[test]
+    fn matches_fields() {
+        assert!(matches("black"));
+        assert!(matches("SCREEN tag:ventura"));
+        assert!(!matches("tag:vent"));
+        assert!(matches(&crate::session_code(7).to_lowercase()));
+        assert!(matches(&format!("code:{}", crate::session_code(7))));
+        assert!(matches("addr:10.0.0.2"));
+        assert!(matches("saved:yes -saved:no"));
+        assert!(matches("status:needs-info"));
+        assert!(matches("assignee:"));
+        assert!(matches("flag:\"gpu hang\""));
+        assert!(matches("fact.device_id:15d8"));
+        assert!(matches("fact.os_version:"));
+        assert!(!matches("fact.device_id:"));
+        assert!(!matches("-black"));
+        assert!(!matches("black wake nope"));
+    }
+
+    #[test]
+    fn hides_archived() {
+        let mut ent = entry();
+        ent.archived = true;
+        let parse = |v: &str| v.parse::<Query>().unwrap();
+        assert!(!parse("black").matches(&ent));
+        assert!(parse("black archived:yes").matches(&ent));
+        assert!(!parse("archived:no").matches(&ent));
+    }
+}
@@ -0,0 +1,454 @@
+//! Redaction of serial numbers, MAC addresses, user names in paths and other sensitive data
+//! logs print. Depending on the config, stored logs are redacted as they arrive or when
+//! they're saved; exports and share links are always redacted while redaction is on.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use wred_server::{LogEntry, Redaction, RedactionReport};
+
+use crate::state::AppState;
+
+/// The earliest point logs are redacted at.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Stage {
+    #[default]
+    Off,
+    /// Complete lines are redacted before they're stored or sent to live tails.
+    Ingest,
+    /// Logs are redacted when they're written to disk.
+    Save,
+    /// Only exports and share links are redacted.
+    Export,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Detector {
+    /// Mac and logic board serial numbers.
+    Serial,
+    Mac,
+    /// The user name in `/Users/<name>`.
+    HomePath,
+    /// SMBIOS UUIDs and ROM.
+    Smbios,
+}
+
+impl Detector {
+    const ALL: [Self; 4] = [Self::Serial, Self::Mac, Self::HomePath, Self::Smbios];
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Serial => "serial",
+            Self::Mac => "mac",
+            Self::HomePath => "home-path",
+            Self::Smbios => "smbios",
+        }
+    }
+
+    const fn pattern(self) -> &'static str {
+        match self {
+            Self::Serial => {
+                r"(?i)\b(?:serial(?:[ _-]?number)?|system-serial|MLB|BoardSerialNumber)\W{1,4}([A-Z0-9]{10,17})\b"
+            }
+            Self::Mac => r"\b(?:[[:xdigit:]]{2}[:-]){5}[[:xdigit:]]{2}\b",
+            Self::HomePath => r"/Users/([^/\s]+)",
+            Self::Smbios => {
+                r"(?i)\b(?:SystemUUID|SmUUID|system-id|IOPlatformUUID|platform-uuid|ROM)\W{1,4}([[:xdigit:]]{8}-?[[:xdigit:]]{4}-?[[:xdigit:]]{4}-?[[:xdigit:]]{4}-?[[:xdigit:]]{12}|[[:xdigit:]]{12})\b"
+            }
+        }
+    }
+}
+
+/// A custom detector. The first capture group is redacted if there is one, otherwise the
+/// whole match.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Redactor {
+    pub name: String,
+    pub pattern: String,
+}
+
+fn all_detectors() -> Vec<Detector> {
+    Detector::ALL.to_vec()
+}
+
+const fn yes() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionConfig {
+    #[serde(default)]
+    pub at: Stage,
+    /// Built-in detectors to use. All of them unless given.
+    #[serde(default = "all_detectors")]
+    pub detectors: Vec<Detector>,
+    #[serde(default)]
+    pub custom: Vec<Redactor>,
+    /// Keep what was redacted from stored logs, for admins to see.
+    #[serde(default = "yes")]
+    pub keep_originals: bool,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            at: Stage::Off,
+            detectors: all_detectors(),
+            custom: Vec::new(),
+            keep_originals: true,
+        }
+    }
+}
+
+/// A span of text to redact, and the detector that found it.
+struct Span {
+    start: usize,
+    end: usize,
+    detector: usize,
+}
+
+#[derive(Debug)]
+pub struct Redactors {
+    at: Stage,
+    keep_originals: bool,
+    /// Detector names and patterns.
+    list: Vec<(String, Regex)>,
+}
+
+impl Redactors {
+    /// # Panics
+    ///
+    /// If a custom pattern isn't a valid regex.
+    pub fn new(config: &RedactionConfig) -> Self {
+        let builtin = config
+            .detectors
+            .iter()
+            .map(|v| (v.name().to_owned(), Regex::new(v.pattern()).unwrap()));
+        let custom = config.custom.iter().map(|v| {
+            let re = Regex::new(&v.pattern)
+                .unwrap_or_else(|e| panic!("Bad redaction pattern for {:?}: {e}", v.name));
+            (v.name.clone(), re)
+        });
+        Self {
+            at: config.at,
+            keep_originals: config.keep_originals,
+            list: builtin.chain(custom).collect(),
+        }
+    }
+
+    pub const fn is_on(&self) -> bool {
+        !matches!(self.at, Stage::Off)
+    }
+
+    pub const fn at(&self) -> Stage {
+        self.at
+    }
+
+    /// Spans of `text` to redact, in order and not overlapping.
+    fn find(&self, text: &str) -> Vec<Span> {
+        let mut spans = Vec::new();
+        for (detector, (_, re)) in self.list.iter().enumerate() {
+            for captures in re.captures_iter(text) {
+                let m = captures.get(1).or_else(|| captures.get(0)).unwrap();
+                if !m.is_empty() {
+                    spans.push(Span {
+                        start: m.start(),
+                        end: m.end(),
+                        detector,
+                    });
+                }
+            }
+        }
+        spans.sort_by_key(|v| (v.start, v.end));
+        let mut end = 0;
+        spans.retain(|v| {
+            let keep = v.start >= end;
+            end = end.max(v.end);
+            keep
+        });
+        spans
+    }
+
+    fn replacement(&self, detector: usize) -> String {
+        format!("[redacted:{}]", self.list[detector].0)
+    }
+
+    /// `text` with its sensitive parts replaced, and what was replaced, with offsets in the
+    /// returned text and where each replaced part ended in `text`.
+    fn apply(&self, text: &str) -> (String, Vec<(Redaction, usize)>) {
+        let mut out = String::with_capacity(text.len());
+        let mut redactions = Vec::new();
+        let mut last = 0;
+        for span in self.find(text) {
+            out.push_str(&text[last..span.start]);
+            let replacement = self.replacement(span.detector);
+            redactions.push((
+                Redaction {
+                    offset: out.len() as u64,
+                    len: u32::try_from(replacement.len()).unwrap_or(u32::MAX),
+                    detector: self.list[span.detector].0.clone(),
+                    original: self
+                        .keep_originals
+                        .then(|| text[span.start..span.end].to_owned()),
+                },
+                span.end,
+            ));
+            out.push_str(&replacement);
+            last = span.end;
+        }
+        out.push_str(&text[last..]);
+        (out, redactions)
+    }
+
+    /// `text` with its sensitive parts replaced, if redaction is on.
+    pub fn redact(&self, text: &str) -> String {
+        if !self.is_on() {
+            return text.to_owned();
+        }
+        self.apply(text).0
+    }
+}
+
+/// 1-based line of each offset in `data`, for offsets in ascending order.
+fn lines_of(data: &str, offsets: impl Iterator<Item = usize>) -> Vec<u32> {
+    let mut line = 1u32;
+    let mut pos = 0;
+    offsets
+        .map(|offset| {
+            let offset = offset.min(data.len());
+            let n = data[pos..offset].matches('\n').count();
+            line = line.saturating_add(u32::try_from(n).unwrap_or(u32::MAX));
+            pos = offset;
+            line
+        })
+        .collect()
+}
+
+impl AppState {
+    /// Redacts the part of `v` that hasn't been yet, up to its last line break, or all of it
+    /// if `all`. Returns whether anything was redacted.
+    pub fn redact_log(&self, v: &mut LogEntry, all: bool) -> bool {
+        if !self.redactors.is_on() {
+            return false;
+        }
+        let start = usize::try_from(v.redacted).unwrap_or(usize::MAX);
+        let Some(rest) = v.data.get(start..) else {
+            return false;
+        };
+        let end = if all {
+            v.data.len()
+        } else {
+            match rest.rfind('\n') {
+                Some(n) => start + n + 1,
+                None => return false,
+            }
+        };
+        let (redacted, found) = self.redactors.apply(&v.data[start..end]);
+        v.redacted = (start + redacted.len()) as u64;
+        if found.is_empty() {
+            return false;
+        }
+
+        // Move when later data was received along with the end of the redaction before it.
+        let mut ends = Vec::with_capacity(found.len());
+        for (mut redaction, old_end) in found {
+            redaction.offset += start as u64;
+            ends.push((
+                (start + old_end) as u64,
+                redaction.offset + u64::from(redaction.len),
+            ));
+            v.redactions.push(redaction);
+        }
+        for received in &mut v.received {
+            if let Some(&(old, new)) = ends.iter().rev().find(|&&(old, _)| old <= received.offset) {
+                received.offset = received.offset - old + new;
+            }
+        }
+        v.data.replace_range(start..end, &redacted);
+        v.parsed.clear();
+        if v.hits.end > start {
+            v.hits = wred_server::RuleHits::default();
+            self.rules.scan(v);
+        }
+        true
+    }
+
+    /// What was redacted from log `v`, and what would be from the part that hasn't been, as
+    /// admins see it.
+    pub fn redaction_report(&self, v: &LogEntry) -> Vec<RedactionReport> {
+        let stored = v.redactions.iter().map(|r| {
+            (
+                usize::try_from(r.offset).unwrap_or(usize::MAX),
+                RedactionReport {
+                    line: 0,
+                    detector: r.detector.clone(),
+                    applied: true,
+                    original: r.original.clone(),
+                },
+            )
+        });
+        let start = usize::try_from(v.redacted)
+            .unwrap_or(usize::MAX)
+            .min(v.data.len());
+        let pending = if self.redactors.is_on() {
+            self.redactors.find(&v.data[start..])
+        } else {
+            Vec::new()
+        };
+        let pending = pending.into_iter().map(|span| {
+            (
+                start + span.start,
+                RedactionReport {
+                    line: 0,
+                    detector: self.redactors.list[span.detector].0.clone(),
+                    applied: false,
+                    original: Some(v.data[start + span.start..start + span.end].to_owned()),
+                },
+            )
+        });
+        let mut reports = stored.chain(pending).collect::<Vec<_>>();
+        reports.sort_by_key(|(offset, _)| *offset);
+        let lines = lines_of(&v.data, reports.iter().map(|(offset, _)| *offset));
+        reports
+            .into_iter()
+            .zip(lines)
+            .map(|((_, mut report), line)| {
+                report.line = line;
+                report
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wred_server::Received;
+
+    use super::*;
+    use crate::state::ServerConfig;
+
+    fn config() -> RedactionConfig {
+        RedactionConfig {
+            at: Stage::Ingest,
+            ..RedactionConfig::default()
+        }
+    }
+
+    fn state(redaction: RedactionConfig) -> AppState {
+        let config = ServerConfig {
+            redaction,
+            ..ServerConfig::default()
+        };
+        AppState::new(config, vec![], vec![], b"key".to_vec())
+    }
+
+    #[test]
+    fn redacts_detected_data() {
+        let redactors = Redactors::new(&config());
+        assert_eq!(
+            redactors.redact("en0 00:1A:2b:3c:4d:5e up"),
+            "en0 [redacted:mac] up"
+        );
+        assert_eq!(
+            redactors.redact("open /Users/alice/Library/x"),
+            "open /Users/[redacted:home-path]/Library/x"
+        );
+        assert_eq!(
+            redactors.redact("Serial Number: C02XL0GHJGH5, MLB=C0290370A8GJHPD1F"),
+            "Serial Number: [redacted:serial], MLB=[redacted:serial]"
+        );
+        assert_eq!(
+            redactors.redact("SystemUUID: 12345678-9ABC-DEF0-1234-56789ABCDEF0"),
+            "SystemUUID: [redacted:smbios]"
+        );
+        assert_eq!(redactors.redact("nothing here"), "nothing here");
+    }
+
+    #[test]
+    fn uses_custom_detectors() {
+        let redactors = Redactors::new(&RedactionConfig {
+            detectors: vec![Detector::Mac],
+            custom: vec![
+                Redactor {
+                    name: "email".to_owned(),
+                    pattern: r"[\w.]+@[\w.]+".to_owned(),
+                },
+                Redactor {
+                    name: "token".to_owned(),
+                    pattern: r"token=(\w+)".to_owned(),
+                },
+            ],
+            ..config()
+        });
+        assert_eq!(
+            redactors.redact("from a.b@c.d token=abc /Users/alice"),
+            "from [redacted:email] token=[redacted:token] /Users/alice"
+        );
+    }
+
+    #[test]
+    fn leaves_text_when_off() {
+        let redactors = Redactors::new(&RedactionConfig::default());
+        assert!(!redactors.is_on());
+        assert_eq!(redactors.redact("00:11:22:33:44:55"), "00:11:22:33:44:55");
+    }
+
+    #[test]
+    fn redacts_logs_a_line_at_a_time() {
+        let state = state(config());
+        let mut v = LogEntry::new(
+            0,
+            "127.0.0.1:1".parse().unwrap(),
+            "mac 00:11:22:33:44:55\nok\n/Users/alice/x".to_owned(),
+        );
+        v.received = [0, 22, 25]
+            .into_iter()
+            .map(|offset| Received { offset, at: 0 })
+            .collect();
+
+        assert!(state.redact_log(&mut v, false));
+        assert_eq!(v.data, "mac [redacted:mac]\nok\n/Users/alice/x");
+        assert_eq!(v.redacted, 22);
+        assert_eq!(
+            v.received.iter().map(|v| v.offset).collect::<Vec<_>>(),
+            [0, 19, 22]
+        );
+        assert_eq!(
+            v.redactions,
+            [Redaction {
+                offset: 4,
+                len: 14,
+                detector: "mac".to_owned(),
+                original: Some("00:11:22:33:44:55".to_owned()),
+            }]
+        );
+        // Nothing more until the last line is complete, or everything is asked for.
+        assert!(!state.redact_log(&mut v, false));
+        assert!(state.redact_log(&mut v, true));
+        assert_eq!(
+            v.data,
+            "mac [redacted:mac]\nok\n/Users/[redacted:home-path]/x"
+        );
+        assert_eq!(v.redacted, v.data.len() as u64);
+        assert_eq!(v.redactions[1].offset, 29);
+        assert_eq!(
+            &v.data[29..29 + v.redactions[1].len as usize],
+            "[redacted:home-path]"
+        );
+        assert!(!state.redact_log(&mut v, true));
+    }
+
+    #[test]
+    fn drops_originals_if_asked() {
+        let state = state(RedactionConfig {
+            keep_originals: false,
+            ..config()
+        });
+        let mut v = LogEntry::new(0, "127.0.0.1:1".parse().unwrap(), "/Users/alice\n".into());
+        assert!(state.redact_log(&mut v, false));
+        assert_eq!(v.redactions[0].original, None);
+        let report = state.redaction_report(&v);
+        assert_eq!(report.len(), 1);
+        assert!(report[0].applied);
+        assert_eq!(report[0].line, 1);
+    }
+}
@@ -1,101 +1,1795 @@
-use actix_web::{delete, get, http::header::ContentType, post, web, HttpResponse, Responder};
+use std::collections::HashSet;
 
-#[get("/all")]
+use actix_web::{
+    delete, get,
+    http::header::{
+        self, CacheControl, CacheDirective, ContentDisposition, ContentRange, ContentRangeSpec,
+        ContentType, DispositionParam, DispositionType, Header, Range,
+    },
+    patch, post, put, web, HttpRequest, HttpResponse, Responder,
+};
+use serde::Deserialize;
+use tokio::sync::broadcast;
+use utoipa::IntoParams;
+
+use crate::{
+    auth::{Identity, Require, Scope},
+    codec::Encoded,
+};
+
+/// How often an idle live tail gets a comment, so proxies don't drop the connection.
+const KEEP_ALIVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// Header carrying the full length of a log, so clients can resume with `?since=`.
+pub const LOG_LENGTH_HEADER: &str = "X-Log-Length";
+
+fn unix_time() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Server version and the optional features it supports.
+#[utoipa::path(
+    context_path = "/api/v1",
+    responses((status = 200, content(
+        (wred_server::ServerInfo = "application/json"),
+        (wred_server::ServerInfo = "application/x-postcard"),
+    )))
+)]
+#[get("/version")]
 #[allow(clippy::unused_async)]
-async fn get_logs(data: web::Data<super::state::AppState>) -> impl Responder {
+async fn get_version() -> impl Responder {
+    use wred_server::capability;
+
+    Encoded(wred_server::ServerInfo {
+        version: env!("CARGO_PKG_VERSION").to_owned(),
+        api_version: wred_server::API_VERSION,
+        capabilities: [
+            capability::RANGES,
+            capability::STREAM,
+            capability::JSON,
+            capability::OPENAPI,
+            capability::SHARE,
+            capability::SESSION_CODES,
+            capability::META,
+            capability::QUERY,
+            capability::TRIAGE,
+            capability::COMMENTS,
+            capability::BULK,
+            capability::ARCHIVE,
+            capability::DOWNLOAD,
+            capability::DIFF,
+            capability::BASELINES,
+            capability::PARSED_LINES,
+            capability::FLAGS,
+            capability::SIGNATURES,
+            capability::FACTS,
+            capability::REDACTION,
+            capability::ADDR_MAP,
+            capability::ERASE,
+            capability::AUDIT,
+        ]
+        .map(str::to_owned)
+        .to_vec(),
+    })
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct LogsQuery {
+    /// Only list logs matching this, e.g. `tag:ventura -saved:yes "black screen"`.
+    q: Option<String>,
+}
+
+/// Summaries of every log, or of those matching a query.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(LogsQuery),
+    responses((status = 200, content(
+        (Vec<wred_server::LogEntryPartial> = "application/json"),
+        (Vec<wred_server::LogEntryPartial> = "application/x-postcard"),
+    )), (status = 400))
+)]
+#[get("/all", wrap = "Require(Scope::Read)")]
+#[allow(clippy::unused_async, clippy::future_not_send)]
+async fn get_logs(
+    req: HttpRequest,
+    query: web::Query<LogsQuery>,
+    data: web::Data<super::state::AppState>,
+    identity: web::ReqData<Identity>,
+) -> HttpResponse {
+    // Without a query, archived logs are listed too, for clients that filter themselves.
+    let filter = match query
+        .q
+        .as_deref()
+        .map(str::parse::<wred_server::query::Query>)
+    {
+        None => None,
+        Some(Ok(v)) => Some(v),
+        Some(Err(e)) => return HttpResponse::BadRequest().body(e),
+    };
     let data = data.into_inner();
     let logs = data.logs.lock().unwrap();
-    let resp: Vec<_> = logs
+    // Filter after masking, so addresses a caller can't see can't be probed either.
+    let ents = logs
         .iter()
-        .map(|(&id, v)| wred_server::LogEntryPartial {
-            id,
-            addr: v.addr,
-            last_updated: v.last_updated,
-            is_saved: data
-                .config
-                .log_dir
-                .join(format!("{id}.log"))
-                .try_exists()
-                .unwrap_or_default(),
+        .map(|(&id, v)| data.mask_partial(data.partial(id, v), &identity))
+        .filter(|v| filter.as_ref().is_none_or(|f| f.matches(v)))
+        .collect::<Vec<_>>();
+    drop(logs);
+    Encoded(ents).respond_to(&req)
+}
+
+/// Live tail of new sessions and appended data as Server-Sent Events with JSON payloads.
+#[utoipa::path(
+    context_path = "/api/v1",
+    responses((status = 200, description = "One `LogEvent` per `data:` line",
+        body = wred_server::LogEvent, content_type = "text/event-stream"))
+)]
+#[get("/stream", wrap = "Require(Scope::Read)")]
+#[allow(clippy::unused_async)]
+async fn stream_logs(
+    data: web::Data<super::state::AppState>,
+    identity: web::ReqData<Identity>,
+) -> impl Responder {
+    let rx = data.events.subscribe();
+    let subscriber = (data.into_inner(), identity.into_inner());
+    let events = futures_util::stream::unfold((rx, subscriber), |(mut rx, subscriber)| async {
+        let (data, identity) = &subscriber;
+        let event = match tokio::time::timeout(KEEP_ALIVE_INTERVAL, rx.recv()).await {
+            Err(_) => None,
+            Ok(Ok(wred_server::LogEvent::Session(v))) => Some(wred_server::LogEvent::Session(
+                data.mask_partial(v, identity),
+            )),
+            Ok(Ok(event)) => Some(event),
+            Ok(Err(broadcast::error::RecvError::Lagged(_))) => Some(wred_server::LogEvent::Lagged),
+            Ok(Err(broadcast::error::RecvError::Closed)) => return None,
+        };
+        let chunk = event.map_or_else(
+            || ": keep-alive\n\n".to_owned(),
+            |event| format!("data: {}\n\n", serde_json::to_string(&event).unwrap()),
+        );
+        Some((
+            Ok::<_, std::convert::Infallible>(web::Bytes::from(chunk)),
+            (rx, subscriber),
+        ))
+    });
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .streaming(events)
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct LogQuery {
+    /// Only return the bytes appended after this offset. Takes precedence over `Range`.
+    since: Option<u64>,
+}
+
+/// The bytes of a log to send.
+#[derive(Debug, PartialEq, Eq)]
+struct LogSlice {
+    start: u64,
+    end: u64,
+    /// Whether it's the one range a `Range` header asked for.
+    partial: bool,
+}
+
+impl LogSlice {
+    /// What to send of a log `total` bytes long, given `since` and the `Range` header, which
+    /// is only honoured for a single range. `None` if either starts past the end.
+    fn new(total: u64, since: Option<u64>, range: Option<Range>) -> Option<Self> {
+        let whole = Self {
+            start: 0,
+            end: total,
+            partial: false,
+        };
+        if let Some(since) = since {
+            return (since <= total).then_some(Self {
+                start: since,
+                ..whole
+            });
+        }
+        let Some(Range::Bytes(specs)) = range else {
+            return Some(whole);
+        };
+        let [spec] = specs.as_slice() else {
+            return Some(whole);
+        };
+        let (first, last) = spec.to_satisfiable_range(total)?;
+        Some(Self {
+            start: first,
+            end: last + 1,
+            partial: true,
         })
-        .collect();
-    postcard::to_allocvec(&resp).map_or_else(
-        |e| HttpResponse::InternalServerError().body(format!("Failed to serialise: {e}")),
-        |v| HttpResponse::Ok().body(v),
+    }
+}
+
+/// Raw log text, optionally only a byte range or whatever was appended after `since`.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("id" = u64, Path), LogQuery),
+    responses(
+        (status = 200, body = String, content_type = "text/plain",
+            headers(("X-Log-Length" = u64, description = "Full length of the log"))),
+        (status = 206, body = String, content_type = "text/plain"),
+        (status = 404),
+        (status = 416),
     )
+)]
+#[get("/{id:[[:digit:]]+}", wrap = "Require(Scope::Read)")]
+#[allow(clippy::unused_async, clippy::future_not_send)]
+async fn get_log(
+    req: HttpRequest,
+    path: web::Path<u64>,
+    query: web::Query<LogQuery>,
+    data: web::Data<super::state::AppState>,
+) -> impl Responder {
+    let id = path.into_inner();
+    let data = data.into_inner();
+    let logs = data.logs.lock().unwrap();
+    let Some(v) = logs.get(&id) else {
+        return HttpResponse::NotFound().finish();
+    };
+    let bytes = v.data.as_bytes();
+    let total = bytes.len() as u64;
+
+    let Some(slice) = LogSlice::new(total, query.since, Range::parse(&req).ok()) else {
+        return HttpResponse::RangeNotSatisfiable()
+            .insert_header((LOG_LENGTH_HEADER, total))
+            .insert_header(ContentRange(ContentRangeSpec::Bytes {
+                range: None,
+                instance_length: Some(total),
+            }))
+            .finish();
+    };
+
+    let mut resp = if slice.partial {
+        let mut resp = HttpResponse::PartialContent();
+        resp.insert_header(ContentRange(ContentRangeSpec::Bytes {
+            range: Some((slice.start, slice.end - 1)),
+            instance_length: Some(total),
+        }));
+        resp
+    } else {
+        HttpResponse::Ok()
+    };
+    #[allow(clippy::cast_possible_truncation)]
+    let body = bytes[slice.start as usize..slice.end as usize].to_vec();
+    drop(logs);
+    resp.content_type(ContentType::plaintext())
+        .insert_header((header::ACCEPT_RANGES, "bytes"))
+        .insert_header((LOG_LENGTH_HEADER, total))
+        .body(body)
 }
 
-#[get("/{id:[[:digit:]]+}")]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct DownloadQuery {
+    /// `text` unless given.
+    format: Option<crate::export::Format>,
+}
+
+/// A log as a file to save, in one of several formats.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("id" = u64, Path), DownloadQuery),
+    responses((status = 200, description = "The log, with a `Content-Disposition` naming it",
+        content(
+            (String = "text/plain"),
+            (Vec<u8> = "application/gzip"),
+            (String = "application/x-ndjson"),
+            (String = "text/html"),
+        )), (status = 401), (status = 403), (status = 404))
+)]
+#[get("/{id:[[:digit:]]+}/download", wrap = "Require(Scope::Read)")]
 #[allow(clippy::unused_async)]
-async fn get_log(
-    path: web::Path<String>,
+async fn download_log(
+    path: web::Path<u64>,
+    query: web::Query<DownloadQuery>,
+    data: web::Data<super::state::AppState>,
+) -> HttpResponse {
+    let id = path.into_inner();
+    let format = query.format.unwrap_or_default();
+    let Some(export) = crate::export::Export::new(data.into_inner(), id, format) else {
+        return HttpResponse::NotFound().finish();
+    };
+    HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(export.file_name())],
+        })
+        .streaming(export.into_stream())
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct LinesQuery {
+    /// Comma-separated subsystems to keep lines of, matched case-insensitively. Every line is
+    /// kept unless given.
+    subsystem: Option<String>,
+}
+
+/// A log split into lines, with each line's subsystem, function and severity picked out.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("id" = u64, Path), LinesQuery),
+    responses((status = 200, content(
+        (Vec<wred_server::parse::ParsedLine> = "application/json"),
+        (Vec<wred_server::parse::ParsedLine> = "application/x-postcard"),
+    )), (status = 401), (status = 403), (status = 404))
+)]
+#[get("/{id:[[:digit:]]+}/lines", wrap = "Require(Scope::Read)")]
+#[allow(clippy::unused_async, clippy::future_not_send)]
+async fn get_lines(
+    req: HttpRequest,
+    path: web::Path<u64>,
+    query: web::Query<LinesQuery>,
     data: web::Data<super::state::AppState>,
 ) -> impl Responder {
-    let id: u64 = path.into_inner().parse().unwrap();
+    let id = path.into_inner();
+    let subsystems = query.subsystem.as_ref().map(|v| {
+        v.split(',')
+            .map(|v| v.trim().to_ascii_lowercase())
+            .collect::<HashSet<_>>()
+    });
+    let keep = |line: &wred_server::parse::ParsedLine| {
+        subsystems.as_ref().is_none_or(|subsystems| {
+            line.subsystem
+                .as_ref()
+                .is_some_and(|v| subsystems.contains(&v.to_ascii_lowercase()))
+        })
+    };
+    let lines = {
+        let mut logs = data.logs.lock().unwrap();
+        let Some(v) = logs.get_mut(&id) else {
+            return HttpResponse::NotFound().finish();
+        };
+        v.parsed.update(&v.data);
+        let mut lines = v
+            .parsed
+            .lines()
+            .iter()
+            .filter(|v| keep(v))
+            .cloned()
+            .collect::<Vec<_>>();
+        lines.extend(v.parsed.rest(&v.data).filter(|v| keep(v)));
+        drop(logs);
+        lines
+    };
+    Encoded(lines).respond_to(&req)
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct FlagsQuery {
+    /// Only lines flagged by the rule with this label.
+    label: Option<String>,
+}
+
+/// Lines of a log the server's rules flagged, in line order. Only complete lines are checked.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("id" = u64, Path), FlagsQuery),
+    responses((status = 200, content(
+        (Vec<wred_server::Flag> = "application/json"),
+        (Vec<wred_server::Flag> = "application/x-postcard"),
+    )), (status = 401), (status = 403), (status = 404))
+)]
+#[get("/{id:[[:digit:]]+}/flags", wrap = "Require(Scope::Read)")]
+#[allow(clippy::unused_async, clippy::future_not_send)]
+async fn get_flags(
+    req: HttpRequest,
+    path: web::Path<u64>,
+    query: web::Query<FlagsQuery>,
+    data: web::Data<super::state::AppState>,
+) -> impl Responder {
+    let id = path.into_inner();
+    let flags = {
+        let logs = data.logs.lock().unwrap();
+        let Some(v) = logs.get(&id) else {
+            return HttpResponse::NotFound().finish();
+        };
+        let flags = data
+            .rules
+            .flags(&v.hits)
+            .filter(|v| query.label.as_ref().is_none_or(|label| &v.label == label))
+            .collect::<Vec<_>>();
+        drop(logs);
+        flags
+    };
+    Encoded(flags).respond_to(&req)
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct DiffQuery {
+    /// Rewrite volatile tokens, like timestamps and pointers, before comparing lines.
+    #[serde(default)]
+    normalize: bool,
+    /// Unchanged lines to keep around each change. Every line is kept unless given.
+    context: Option<usize>,
+}
+
+/// Line diff from one log to another.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("old" = u64, Path), ("new" = u64, Path), DiffQuery),
+    responses((status = 200, content(
+        (wred_server::LogDiff = "application/json"),
+        (wred_server::LogDiff = "application/x-postcard"),
+    )), (status = 401), (status = 403), (status = 404))
+)]
+#[get(
+    "/diff/{old:[[:digit:]]+}/{new:[[:digit:]]+}",
+    wrap = "Require(Scope::Read)"
+)]
+#[allow(clippy::future_not_send)]
+async fn diff_logs(
+    req: HttpRequest,
+    path: web::Path<(u64, u64)>,
+    query: web::Query<DiffQuery>,
+    data: web::Data<super::state::AppState>,
+) -> actix_web::Result<HttpResponse> {
+    let (old, new) = path.into_inner();
+    let texts = {
+        let logs = data.logs.lock().unwrap();
+        let texts = logs
+            .get(&old)
+            .zip(logs.get(&new))
+            .map(|(old, new)| (old.data.clone(), new.data.clone()));
+        drop(logs);
+        texts
+    };
+    let Some((old, new)) = texts else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
     let data = data.into_inner();
+    let diff = web::block(move || {
+        let normalizers = if query.normalize {
+            data.normalizers.as_slice()
+        } else {
+            &[]
+        };
+        crate::diff::diff(&old, &new, normalizers, query.context)
+    })
+    .await?;
+    Ok(Encoded(diff).respond_to(&req))
+}
+
+/// Removes a log from memory and disk.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("id" = u64, Path)),
+    responses((status = 200), (status = 401), (status = 403), (status = 404)),
+    security(("token" = ["delete"]))
+)]
+#[delete("/{id:[[:digit:]]+}", wrap = "Require(Scope::Delete)")]
+async fn delete_log(
+    path: web::Path<u64>,
+    data: web::Data<super::state::AppState>,
+) -> std::io::Result<HttpResponse> {
+    let id = path.into_inner();
+    if data.remove_log(id).await? {
+        Ok(HttpResponse::Ok().finish())
+    } else {
+        Ok(HttpResponse::NotFound().finish())
+    }
+}
+
+/// Writes a log to disk so it survives restarts.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("id" = u64, Path)),
+    responses((status = 200), (status = 401), (status = 403), (status = 404)),
+    security(("token" = ["save"]))
+)]
+#[post("/{id:[[:digit:]]+}", wrap = "Require(Scope::Save)")]
+async fn save_log(
+    path: web::Path<u64>,
+    data: web::Data<super::state::AppState>,
+) -> std::io::Result<HttpResponse> {
+    let id = path.into_inner();
+    if data.save_log(id).await? {
+        Ok(HttpResponse::Ok().finish())
+    } else {
+        Ok(HttpResponse::NotFound().finish())
+    }
+}
+
+/// Removes a log from disk, keeping it in memory until the server restarts.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("id" = u64, Path)),
+    responses((status = 200), (status = 401), (status = 403), (status = 404)),
+    security(("token" = ["save"]))
+)]
+#[post("/{id:[[:digit:]]+}/unsave", wrap = "Require(Scope::Save)")]
+async fn unsave_log(
+    path: web::Path<u64>,
+    data: web::Data<super::state::AppState>,
+) -> std::io::Result<HttpResponse> {
+    let id = path.into_inner();
+    if data.unsave_log(id).await? {
+        Ok(HttpResponse::Ok().finish())
+    } else {
+        Ok(HttpResponse::NotFound().finish())
+    }
+}
+
+/// Sets whether a log is archived, rewriting it to disk if it's saved.
+async fn set_archived(
+    id: u64,
+    archived: bool,
+    data: &super::state::AppState,
+) -> std::io::Result<HttpResponse> {
+    {
+        let mut logs = data.logs.lock().unwrap();
+        let Some(v) = logs.get_mut(&id) else {
+            return Ok(HttpResponse::NotFound().finish());
+        };
+        v.archived = archived;
+        data.notify(wred_server::LogEvent::Session(data.partial(id, v)));
+        drop(logs);
+    }
+    if data.is_saved(id) {
+        data.write_log(id).await?;
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Hides a log from listings without deleting it.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("id" = u64, Path)),
+    responses((status = 200), (status = 401), (status = 403), (status = 404)),
+    security(("token" = ["save"]))
+)]
+#[post("/{id:[[:digit:]]+}/archive", wrap = "Require(Scope::Save)")]
+async fn archive_log(
+    path: web::Path<u64>,
+    data: web::Data<super::state::AppState>,
+) -> std::io::Result<HttpResponse> {
+    set_archived(path.into_inner(), true, &data).await
+}
+
+/// Brings an archived log back into listings.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("id" = u64, Path)),
+    responses((status = 200), (status = 401), (status = 403), (status = 404)),
+    security(("token" = ["save"]))
+)]
+#[post("/{id:[[:digit:]]+}/unarchive", wrap = "Require(Scope::Save)")]
+async fn unarchive_log(
+    path: web::Path<u64>,
+    data: web::Data<super::state::AppState>,
+) -> std::io::Result<HttpResponse> {
+    set_archived(path.into_inner(), false, &data).await
+}
+
+/// Token that lets the submitter of a log read and delete it, and nothing else.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("id" = u64, Path)),
+    responses((status = 200, content(
+        (String = "application/json"),
+        (String = "application/x-postcard"),
+    )), (status = 401), (status = 403), (status = 404)),
+    security(("token" = ["admin"]))
+)]
+#[get("/{id:[[:digit:]]+}/claim", wrap = "Require(Scope::Admin)")]
+#[allow(clippy::unused_async, clippy::future_not_send)]
+async fn get_claim_token(
+    req: HttpRequest,
+    path: web::Path<u64>,
+    data: web::Data<super::state::AppState>,
+) -> HttpResponse {
+    let id = path.into_inner();
+    if data.logs.lock().unwrap().contains_key(&id) {
+        Encoded(crate::signing::claim_token(data.signing_key(), id)).respond_to(&req)
+    } else {
+        HttpResponse::NotFound().finish()
+    }
+}
+
+/// Mints a link that shows a log, or some of its lines, to anyone until it expires.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("id" = u64, Path)),
+    request_body(content(
+        (wred_server::ShareRequest = "application/json"),
+        (wred_server::ShareRequest = "application/x-postcard"),
+    )),
+    responses((status = 200, content(
+        (wred_server::ShareLink = "application/json"),
+        (wred_server::ShareLink = "application/x-postcard"),
+    )), (status = 401), (status = 403), (status = 404)),
+    security(("token" = ["save"]))
+)]
+#[post("/{id:[[:digit:]]+}/share", wrap = "Require(Scope::Save)")]
+#[allow(clippy::unused_async, clippy::future_not_send)]
+async fn share_log(
+    req: HttpRequest,
+    path: web::Path<u64>,
+    body: Encoded<wred_server::ShareRequest>,
+    data: web::Data<super::state::AppState>,
+) -> HttpResponse {
+    let id = path.into_inner();
+    if !data.logs.lock().unwrap().contains_key(&id) {
+        return HttpResponse::NotFound().finish();
+    }
+    let wred_server::ShareRequest { expires_in, lines } = body.0;
+    let expires = unix_time().saturating_add(expires_in);
+    let sig = data.share_signature(id, expires, lines);
+    let lines = lines.map(|v| format!("&lines={v}")).unwrap_or_default();
+    Encoded(wred_server::ShareLink {
+        path: format!(
+            "/api/v{}/shared/{id}?expires={expires}{lines}&sig={sig}",
+            wred_server::API_VERSION
+        ),
+        expires,
+    })
+    .respond_to(&req)
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ShareQuery {
+    expires: u64,
+    /// `first-last`, 1-based and inclusive.
+    lines: Option<String>,
+    sig: String,
+}
+
+/// A log opened through a share link. Needs no token, only a valid signature.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("id" = u64, Path), ShareQuery),
+    responses(
+        (status = 200, body = String, content_type = "text/plain"),
+        (status = 400),
+        (status = 403),
+        (status = 404),
+        (status = 410),
+    )
+)]
+#[get("/shared/{id:[[:digit:]]+}")]
+#[allow(clippy::unused_async)]
+async fn get_shared_log(
+    path: web::Path<u64>,
+    query: web::Query<ShareQuery>,
+    data: web::Data<super::state::AppState>,
+) -> HttpResponse {
+    let id = path.into_inner();
+    let lines = match query
+        .lines
+        .as_deref()
+        .map(str::parse::<wred_server::LineRange>)
+    {
+        None => None,
+        Some(Ok(v)) => Some(v),
+        Some(Err(e)) => return HttpResponse::BadRequest().body(e),
+    };
+    let sig = data.share_signature(id, query.expires, lines);
+    if !crate::signing::constant_time_eq(sig.as_bytes(), query.sig.as_bytes()) {
+        return HttpResponse::Forbidden().body("Invalid or revoked link");
+    }
+    if unix_time() >= query.expires {
+        return HttpResponse::Gone().body("Link expired");
+    }
+
     let logs = data.logs.lock().unwrap();
-    logs.get(&id).map_or_else(
+    let Some(v) = logs.get(&id) else {
+        return HttpResponse::NotFound().finish();
+    };
+    let body = lines.map_or_else(
+        || v.data.clone(),
+        |lines| {
+            v.data
+                .split_inclusive('\n')
+                .skip(lines.first as usize - 1)
+                .take((lines.last - lines.first) as usize + 1)
+                .collect()
+        },
+    );
+    drop(logs);
+    HttpResponse::Ok()
+        .content_type(ContentType::plaintext())
+        .body(data.redactors.redact(&body))
+}
+
+/// Known-issue signatures, oldest first.
+#[utoipa::path(
+    context_path = "/api/v1",
+    responses((status = 200, content(
+        (Vec<wred_server::Signature> = "application/json"),
+        (Vec<wred_server::Signature> = "application/x-postcard"),
+    )), (status = 401), (status = 403))
+)]
+#[get("/signatures", wrap = "Require(Scope::Read)")]
+#[allow(clippy::unused_async, clippy::future_not_send)]
+async fn get_signatures(
+    req: HttpRequest,
+    data: web::Data<super::state::AppState>,
+) -> impl Responder {
+    let signatures = data.signatures.lock().unwrap().list();
+    Encoded(signatures).respond_to(&req)
+}
+
+/// Adds a known-issue signature and matches every log against it.
+#[utoipa::path(
+    context_path = "/api/v1",
+    request_body(content(
+        (wred_server::NewSignature = "application/json"),
+        (wred_server::NewSignature = "application/x-postcard"),
+    )),
+    responses((status = 200, content(
+        (wred_server::Signature = "application/json"),
+        (wred_server::Signature = "application/x-postcard"),
+    )), (status = 400), (status = 401), (status = 403)),
+    security(("token" = ["admin"]))
+)]
+#[post("/signatures", wrap = "Require(Scope::Admin)")]
+#[allow(clippy::future_not_send)]
+async fn add_signature(
+    req: HttpRequest,
+    body: Encoded<wred_server::NewSignature>,
+    identity: web::ReqData<Identity>,
+    data: web::Data<super::state::AppState>,
+) -> actix_web::Result<HttpResponse> {
+    let signature = match data
+        .add_signature(body.0, identity.into_inner().name)
+        .await?
+    {
+        Ok(v) => v,
+        Err(e) => return Ok(HttpResponse::BadRequest().body(e)),
+    };
+    let state = data.clone().into_inner();
+    let changed = web::block(move || state.match_all()).await?;
+    crate::analyzer::persist(&data, changed).await?;
+    Ok(Encoded(signature).respond_to(&req))
+}
+
+/// Removes a known-issue signature.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("id" = u64, Path)),
+    responses((status = 200), (status = 401), (status = 403), (status = 404)),
+    security(("token" = ["admin"]))
+)]
+#[delete("/signatures/{id:[[:digit:]]+}", wrap = "Require(Scope::Admin)")]
+async fn delete_signature(
+    path: web::Path<u64>,
+    data: web::Data<super::state::AppState>,
+) -> std::io::Result<HttpResponse> {
+    let id = path.into_inner();
+    let Some(changed) = data.remove_signature(id).await? else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    crate::analyzer::persist(&data, changed).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// A signature drafted from a log, to edit and add: its title, and patterns for the first
+/// lines the rules flagged.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("id" = u64, Path)),
+    responses((status = 200, content(
+        (wred_server::NewSignature = "application/json"),
+        (wred_server::NewSignature = "application/x-postcard"),
+    )), (status = 401), (status = 403), (status = 404)),
+    security(("token" = ["admin"]))
+)]
+#[get("/{id:[[:digit:]]+}/signature-draft", wrap = "Require(Scope::Admin)")]
+#[allow(clippy::unused_async, clippy::future_not_send)]
+async fn draft_signature(
+    req: HttpRequest,
+    path: web::Path<u64>,
+    data: web::Data<super::state::AppState>,
+) -> HttpResponse {
+    let id = path.into_inner();
+    data.draft_signature(id).map_or_else(
         || HttpResponse::NotFound().finish(),
-        |v| {
-            HttpResponse::Ok()
-                .content_type(ContentType::plaintext())
-                .body(v.data.clone())
+        |v| Encoded(v).respond_to(&req),
+    )
+}
+
+/// What the server redacted from a log, and what it would redact from exports and share
+/// links, with the originals if it keeps them.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("id" = u64, Path)),
+    responses((status = 200, content(
+        (Vec<wred_server::RedactionReport> = "application/json"),
+        (Vec<wred_server::RedactionReport> = "application/x-postcard"),
+    )), (status = 401), (status = 403), (status = 404)),
+    security(("token" = ["admin"]))
+)]
+#[get("/{id:[[:digit:]]+}/redactions", wrap = "Require(Scope::Admin)")]
+#[allow(clippy::unused_async, clippy::future_not_send)]
+async fn get_redactions(
+    req: HttpRequest,
+    path: web::Path<u64>,
+    data: web::Data<super::state::AppState>,
+) -> HttpResponse {
+    let id = path.into_inner();
+    let logs = data.logs.lock().unwrap();
+    let Some(v) = logs.get(&id) else {
+        return HttpResponse::NotFound().finish();
+    };
+    let reports = data.redaction_report(v);
+    drop(logs);
+    Encoded(reports).respond_to(&req)
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct AddrMapQuery {
+    /// Stored pseudonym to look up. Every mapping is listed unless given.
+    addr: Option<String>,
+}
+
+/// Which addresses the pseudonyms stored in place of submitter addresses stand for, for as
+/// long as the server keeps them.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(AddrMapQuery),
+    responses((status = 200, content(
+        (Vec<wred_server::AddrMapping> = "application/json"),
+        (Vec<wred_server::AddrMapping> = "application/x-postcard"),
+    )), (status = 400), (status = 401), (status = 403)),
+    security(("token" = ["admin"]))
+)]
+#[get("/addr-map", wrap = "Require(Scope::Admin)")]
+#[allow(clippy::unused_async, clippy::future_not_send)]
+async fn get_addr_map(
+    req: HttpRequest,
+    query: web::Query<AddrMapQuery>,
+    data: web::Data<super::state::AppState>,
+) -> HttpResponse {
+    let pseudonym = match query.addr.as_deref().map(str::parse::<std::net::IpAddr>) {
+        None => None,
+        Some(Ok(v)) => Some(v),
+        Some(Err(e)) => return HttpResponse::BadRequest().body(e.to_string()),
+    };
+    Encoded(data.addr_mappings(pseudonym)).respond_to(&req)
+}
+
+/// Erases every log tied to a submitter's IP, a session code or a claim token, with the
+/// comments on them, and records what was erased.
+#[utoipa::path(
+    context_path = "/api/v1",
+    request_body(content(
+        (wred_server::ErasureSubject = "application/json"),
+        (wred_server::ErasureSubject = "application/x-postcard"),
+    )),
+    responses((status = 200, description = "What was erased", content(
+        (wred_server::ErasureReport = "application/json"),
+        (wred_server::ErasureReport = "application/x-postcard"),
+    )), (status = 400), (status = 401), (status = 403)),
+    security(("token" = ["admin"]))
+)]
+#[post("/erase", wrap = "Require(Scope::Admin)")]
+#[allow(clippy::future_not_send)]
+async fn erase_subject(
+    req: HttpRequest,
+    body: Encoded<wred_server::ErasureSubject>,
+    data: web::Data<super::state::AppState>,
+    identity: web::ReqData<Identity>,
+) -> std::io::Result<HttpResponse> {
+    Ok(
+        match data.erase_subject(&body.0, identity.name.clone()).await? {
+            Ok(report) => Encoded(report).respond_to(&req),
+            Err(e) => HttpResponse::BadRequest().body(e),
         },
     )
 }
 
-#[delete("/{id:[[:digit:]]+}")]
-async fn delete_log(
+/// Erasures so far, oldest first.
+#[utoipa::path(
+    context_path = "/api/v1",
+    responses((status = 200, content(
+        (Vec<wred_server::ErasureReport> = "application/json"),
+        (Vec<wred_server::ErasureReport> = "application/x-postcard"),
+    )), (status = 401), (status = 403)),
+    security(("token" = ["admin"]))
+)]
+#[get("/erasures", wrap = "Require(Scope::Admin)")]
+#[allow(clippy::future_not_send)]
+async fn get_erasures(
+    req: HttpRequest,
+    data: web::Data<super::state::AppState>,
+) -> std::io::Result<HttpResponse> {
+    Ok(Encoded(data.erasure_reports().await?).respond_to(&req))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct AuditQuery {
+    /// Token name the calls were made with.
+    by: Option<String>,
+    /// ID the calls acted on.
+    target: Option<u64>,
+    /// Unix timestamp in microseconds to list calls from.
+    since: Option<u64>,
+    /// Unix timestamp in microseconds to list calls before.
+    until: Option<u64>,
+    /// Most calls to list, the latest ones. 1000 unless given.
+    limit: Option<usize>,
+}
+
+/// Calls that could have changed something, from the audit log, oldest first.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(AuditQuery),
+    responses((status = 200, content(
+        (Vec<wred_server::AuditEntry> = "application/json"),
+        (Vec<wred_server::AuditEntry> = "application/x-postcard"),
+    )), (status = 401), (status = 403)),
+    security(("token" = ["admin"]))
+)]
+#[get("/audit", wrap = "Require(Scope::Admin)")]
+#[allow(clippy::future_not_send)]
+async fn get_audit(
+    req: HttpRequest,
+    query: web::Query<AuditQuery>,
+    data: web::Data<super::state::AppState>,
+) -> std::io::Result<HttpResponse> {
+    let mut entries = data
+        .audit_entries(|v| {
+            query.by.as_ref().is_none_or(|by| v.by.as_ref() == Some(by))
+                && query.target.is_none_or(|target| v.target == Some(target))
+                && query.since.is_none_or(|since| v.at >= since)
+                && query.until.is_none_or(|until| v.at < until)
+        })
+        .await?;
+    let limit = query.limit.unwrap_or(1000);
+    entries.drain(..entries.len().saturating_sub(limit));
+    Ok(Encoded(entries).respond_to(&req))
+}
+
+/// Revokes every share link handed out so far.
+#[utoipa::path(
+    context_path = "/api/v1",
+    responses((status = 200, description = "The new key generation", content(
+        (u64 = "application/json"),
+        (u64 = "application/x-postcard"),
+    )), (status = 401), (status = 403)),
+    security(("token" = ["admin"]))
+)]
+#[post("/share/rotate", wrap = "Require(Scope::Admin)")]
+#[allow(clippy::future_not_send)]
+async fn rotate_share_links(
+    req: HttpRequest,
+    data: web::Data<super::state::AppState>,
+) -> std::io::Result<HttpResponse> {
+    let generation = data.rotate_share_generation().await?;
+    Ok(Encoded(generation).respond_to(&req))
+}
+
+/// IDs of the sessions with a code, as shown in the client or sent over the logger socket.
+/// Usually one, but codes are short enough to collide.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("code" = String, Path, example = "RED-7K3QX")),
+    responses((status = 200, content(
+        (Vec<u64> = "application/json"),
+        (Vec<u64> = "application/x-postcard"),
+    )), (status = 400), (status = 404))
+)]
+#[get("/code/{code}", wrap = "Require(Scope::Read)")]
+#[allow(clippy::unused_async, clippy::future_not_send)]
+async fn resolve_session_code(
+    req: HttpRequest,
     path: web::Path<String>,
     data: web::Data<super::state::AppState>,
-    body: web::Bytes,
+) -> HttpResponse {
+    let Some(code) = wred_server::parse_session_code(&path) else {
+        return HttpResponse::BadRequest().body("Not a session code");
+    };
+    let mut ids: Vec<u64> = data
+        .logs
+        .lock()
+        .unwrap()
+        .keys()
+        .copied()
+        .filter(|&id| wred_server::session_code(id) == code)
+        .collect();
+    if ids.is_empty() {
+        return HttpResponse::NotFound().finish();
+    }
+    ids.sort_unstable();
+    Encoded(ids).respond_to(&req)
+}
+
+/// Changes a log's title, notes or tags. Saved logs are rewritten to disk to keep them.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("id" = u64, Path)),
+    request_body(content(
+        (wred_server::LogMetaPatch = "application/json"),
+        (wred_server::LogMetaPatch = "application/x-postcard"),
+    )),
+    responses((status = 200, description = "The updated metadata", content(
+        (wred_server::LogMeta = "application/json"),
+        (wred_server::LogMeta = "application/x-postcard"),
+    )), (status = 401), (status = 403), (status = 404)),
+    security(("token" = ["save"]))
+)]
+#[patch("/{id:[[:digit:]]+}", wrap = "Require(Scope::Save)")]
+#[allow(clippy::future_not_send)]
+async fn patch_log(
+    req: HttpRequest,
+    path: web::Path<u64>,
+    body: Encoded<wred_server::LogMetaPatch>,
+    data: web::Data<super::state::AppState>,
 ) -> std::io::Result<HttpResponse> {
-    let id: u64 = path.into_inner().parse().unwrap();
-    let data = data.into_inner();
+    let id = path.into_inner();
+    let meta = {
+        let mut logs = data.logs.lock().unwrap();
+        let Some(v) = logs.get_mut(&id) else {
+            return Ok(HttpResponse::NotFound().finish());
+        };
+        v.meta.apply(body.0);
+        data.notify(wred_server::LogEvent::Session(data.partial(id, v)));
+        let meta = v.meta.clone();
+        drop(logs);
+        meta
+    };
+    if data.is_saved(id) {
+        data.write_log(id).await?;
+    }
+    Ok(Encoded(meta).respond_to(&req))
+}
 
-    let secret: String = postcard::from_bytes(&body)
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-    if secret == data.config.secret {
-        if data.logs.lock().unwrap().remove(&id).is_none() {
-            Ok(HttpResponse::NotFound().finish())
-        } else {
-            let path = data.config.log_dir.join(format!("{id}.log"));
-            if path.exists() {
-                tokio::fs::remove_file(path).await?;
-            }
-            Ok(HttpResponse::Ok().finish())
+/// Records a triage change and rewrites the log to disk if it's saved.
+#[allow(clippy::future_not_send)]
+async fn record_triage(
+    req: &HttpRequest,
+    id: u64,
+    data: &super::state::AppState,
+    identity: &Identity,
+    change: wred_server::TriageChange,
+) -> std::io::Result<HttpResponse> {
+    let Some(entry) = data.record_triage(id, identity, change) else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    if data.is_saved(id) {
+        data.write_log(id).await?;
+    }
+    Ok(Encoded(entry).respond_to(req))
+}
+
+/// Checks that log `id` can be moved to `status`.
+fn check_status(
+    data: &super::state::AppState,
+    id: u64,
+    status: wred_server::Status,
+) -> Result<(), String> {
+    if let wred_server::Status::DuplicateOf(original) = status {
+        if original == id || !data.logs.lock().unwrap().contains_key(&original) {
+            return Err("No other log with that ID".to_owned());
         }
-    } else {
-        Ok(HttpResponse::Unauthorized().finish())
     }
+    Ok(())
 }
 
-#[post("/{id:[[:digit:]]+}")]
-async fn save_log(
-    path: web::Path<String>,
+/// Moves a log to another triage status.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("id" = u64, Path)),
+    request_body(content(
+        (wred_server::Status = "application/json"),
+        (wred_server::Status = "application/x-postcard"),
+    )),
+    responses((status = 200, description = "The recorded change", content(
+        (wred_server::HistoryEntry = "application/json"),
+        (wred_server::HistoryEntry = "application/x-postcard"),
+    )), (status = 400), (status = 401), (status = 403), (status = 404)),
+    security(("token" = ["save"]))
+)]
+#[put("/{id:[[:digit:]]+}/status", wrap = "Require(Scope::Save)")]
+#[allow(clippy::future_not_send)]
+async fn set_status(
+    req: HttpRequest,
+    path: web::Path<u64>,
+    body: Encoded<wred_server::Status>,
     data: web::Data<super::state::AppState>,
-    body: web::Bytes,
+    identity: web::ReqData<Identity>,
 ) -> std::io::Result<HttpResponse> {
-    let id: u64 = path.into_inner().parse().unwrap();
-    let data = data.into_inner();
+    let id = path.into_inner();
+    if let Err(e) = check_status(&data, id, body.0) {
+        return Ok(HttpResponse::BadRequest().body(e));
+    }
+    let change = wred_server::TriageChange::Status(body.0);
+    record_triage(&req, id, &data, &identity, change).await
+}
 
-    let secret: String = postcard::from_bytes(&body)
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-    if secret == data.config.secret {
-        let v = data
-            .logs
-            .lock()
-            .unwrap()
-            .get(&id)
-            .map(|v| postcard::to_allocvec(&(id, v)));
-        if let Some(v) = v {
-            let resp = v.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-            tokio::fs::write(data.config.log_dir.join(format!("{id}.log")), &resp).await?;
-            Ok(HttpResponse::Ok().finish())
+/// Assigns a log to someone, or unassigns it with `null` or an empty name.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("id" = u64, Path)),
+    request_body(content(
+        (Option<String> = "application/json"),
+        (Option<String> = "application/x-postcard"),
+    )),
+    responses((status = 200, description = "The recorded change", content(
+        (wred_server::HistoryEntry = "application/json"),
+        (wred_server::HistoryEntry = "application/x-postcard"),
+    )), (status = 401), (status = 403), (status = 404)),
+    security(("token" = ["save"]))
+)]
+#[put("/{id:[[:digit:]]+}/assignee", wrap = "Require(Scope::Save)")]
+#[allow(clippy::future_not_send)]
+async fn set_assignee(
+    req: HttpRequest,
+    path: web::Path<u64>,
+    body: Encoded<Option<String>>,
+    data: web::Data<super::state::AppState>,
+    identity: web::ReqData<Identity>,
+) -> std::io::Result<HttpResponse> {
+    let id = path.into_inner();
+    let assignee = body
+        .0
+        .map(|v| v.trim().to_owned())
+        .filter(|v| !v.is_empty());
+    let change = wred_server::TriageChange::Assignee(assignee);
+    record_triage(&req, id, &data, &identity, change).await
+}
+
+/// Every triage change made to a log, oldest first.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("id" = u64, Path)),
+    responses((status = 200, content(
+        (Vec<wred_server::HistoryEntry> = "application/json"),
+        (Vec<wred_server::HistoryEntry> = "application/x-postcard"),
+    )), (status = 404))
+)]
+#[get("/{id:[[:digit:]]+}/history", wrap = "Require(Scope::Read)")]
+#[allow(clippy::unused_async, clippy::future_not_send)]
+async fn get_history(
+    req: HttpRequest,
+    path: web::Path<u64>,
+    data: web::Data<super::state::AppState>,
+) -> HttpResponse {
+    let id = path.into_inner();
+    let history = data
+        .logs
+        .lock()
+        .unwrap()
+        .get(&id)
+        .map(|v| v.triage.history.clone());
+    history.map_or_else(
+        || HttpResponse::NotFound().finish(),
+        |v| Encoded(v).respond_to(&req),
+    )
+}
+
+/// Comments on a log, oldest first.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("id" = u64, Path)),
+    responses((status = 200, content(
+        (Vec<wred_server::Comment> = "application/json"),
+        (Vec<wred_server::Comment> = "application/x-postcard"),
+    )), (status = 404))
+)]
+#[get("/{id:[[:digit:]]+}/comments", wrap = "Require(Scope::Read)")]
+#[allow(clippy::unused_async, clippy::future_not_send)]
+async fn get_comments(
+    req: HttpRequest,
+    path: web::Path<u64>,
+    data: web::Data<super::state::AppState>,
+) -> HttpResponse {
+    let id = path.into_inner();
+    let comments = data
+        .logs
+        .lock()
+        .unwrap()
+        .get(&id)
+        .map(|v| v.comments.clone());
+    comments.map_or_else(
+        || HttpResponse::NotFound().finish(),
+        |v| Encoded(v).respond_to(&req),
+    )
+}
+
+/// Tells live tails a log's comments changed, rewrites it to disk if it's saved, and responds
+/// with `v`.
+#[allow(clippy::future_not_send)]
+async fn comments_changed<T: serde::Serialize>(
+    req: &HttpRequest,
+    id: u64,
+    data: &super::state::AppState,
+    v: T,
+) -> std::io::Result<HttpResponse> {
+    data.notify(wred_server::LogEvent::Comments(id));
+    if data.is_saved(id) {
+        data.write_log(id).await?;
+    }
+    Ok(Encoded(v).respond_to(req))
+}
+
+/// Comments on a log, optionally about a range of its lines.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("id" = u64, Path)),
+    request_body(content(
+        (wred_server::CommentBody = "application/json"),
+        (wred_server::CommentBody = "application/x-postcard"),
+    )),
+    responses((status = 200, description = "The new comment", content(
+        (wred_server::Comment = "application/json"),
+        (wred_server::Comment = "application/x-postcard"),
+    )), (status = 400), (status = 401), (status = 403), (status = 404)),
+    security(("token" = ["save"]))
+)]
+#[post("/{id:[[:digit:]]+}/comments", wrap = "Require(Scope::Save)")]
+#[allow(clippy::future_not_send)]
+async fn add_comment(
+    req: HttpRequest,
+    path: web::Path<u64>,
+    body: Encoded<wred_server::CommentBody>,
+    data: web::Data<super::state::AppState>,
+    identity: web::ReqData<Identity>,
+) -> std::io::Result<HttpResponse> {
+    let id = path.into_inner();
+    let wred_server::CommentBody { lines, body } = body.0;
+    if body.trim().is_empty() {
+        return Ok(HttpResponse::BadRequest().body("Empty comment"));
+    }
+    let comment = {
+        let mut logs = data.logs.lock().unwrap();
+        let Some(v) = logs.get_mut(&id) else {
+            return Ok(HttpResponse::NotFound().finish());
+        };
+        let comment = wred_server::Comment {
+            id: v.next_comment_id,
+            author: identity.name.clone(),
+            created: crate::state::unix_micros(),
+            edited: None,
+            lines,
+            body,
+        };
+        v.next_comment_id += 1;
+        v.comments.push(comment.clone());
+        drop(logs);
+        comment
+    };
+    comments_changed(&req, id, &data, comment).await
+}
+
+/// Replaces a comment. Only its author or an admin may.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("id" = u64, Path), ("comment" = u64, Path)),
+    request_body(content(
+        (wred_server::CommentBody = "application/json"),
+        (wred_server::CommentBody = "application/x-postcard"),
+    )),
+    responses((status = 200, description = "The edited comment", content(
+        (wred_server::Comment = "application/json"),
+        (wred_server::Comment = "application/x-postcard"),
+    )), (status = 400), (status = 401), (status = 403), (status = 404)),
+    security(("token" = ["save"]))
+)]
+#[put(
+    "/{id:[[:digit:]]+}/comments/{comment:[[:digit:]]+}",
+    wrap = "Require(Scope::Save)"
+)]
+#[allow(clippy::future_not_send)]
+async fn edit_comment(
+    req: HttpRequest,
+    path: web::Path<(u64, u64)>,
+    body: Encoded<wred_server::CommentBody>,
+    data: web::Data<super::state::AppState>,
+    identity: web::ReqData<Identity>,
+) -> std::io::Result<HttpResponse> {
+    let (id, comment_id) = path.into_inner();
+    let wred_server::CommentBody { lines, body } = body.0;
+    if body.trim().is_empty() {
+        return Ok(HttpResponse::BadRequest().body("Empty comment"));
+    }
+    let comment = {
+        let mut logs = data.logs.lock().unwrap();
+        let Some(comment) = logs
+            .get_mut(&id)
+            .and_then(|v| v.comments.iter_mut().find(|v| v.id == comment_id))
+        else {
+            return Ok(HttpResponse::NotFound().finish());
+        };
+        if comment.author != identity.name && !identity.allows(Scope::Admin) {
+            return Ok(HttpResponse::Forbidden().body("Only the author may edit a comment"));
+        }
+        comment.lines = lines;
+        comment.body = body;
+        comment.edited = Some(crate::state::unix_micros());
+        let comment = comment.clone();
+        drop(logs);
+        comment
+    };
+    comments_changed(&req, id, &data, comment).await
+}
+
+/// Removes a comment. Only its author or an admin may.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("id" = u64, Path), ("comment" = u64, Path)),
+    responses((status = 200), (status = 401), (status = 403), (status = 404)),
+    security(("token" = ["save"]))
+)]
+#[delete(
+    "/{id:[[:digit:]]+}/comments/{comment:[[:digit:]]+}",
+    wrap = "Require(Scope::Save)"
+)]
+#[allow(clippy::future_not_send)]
+async fn delete_comment(
+    req: HttpRequest,
+    path: web::Path<(u64, u64)>,
+    data: web::Data<super::state::AppState>,
+    identity: web::ReqData<Identity>,
+) -> std::io::Result<HttpResponse> {
+    let (id, comment_id) = path.into_inner();
+    {
+        let mut logs = data.logs.lock().unwrap();
+        let Some(comments) = logs.get_mut(&id).map(|v| &mut v.comments) else {
+            return Ok(HttpResponse::NotFound().finish());
+        };
+        let Some(i) = comments.iter().position(|v| v.id == comment_id) else {
+            return Ok(HttpResponse::NotFound().finish());
+        };
+        if comments[i].author != identity.name && !identity.allows(Scope::Admin) {
+            return Ok(HttpResponse::Forbidden().body("Only the author may delete a comment"));
+        }
+        comments.remove(i);
+        drop(logs);
+    }
+    comments_changed(&req, id, &data, ()).await
+}
+
+/// IDs of the logs a bulk operation applies to, in the order given or oldest first for a query.
+fn select(
+    data: &super::state::AppState,
+    identity: &Identity,
+    selection: &wred_server::Selection,
+) -> Result<Vec<u64>, String> {
+    match selection {
+        wred_server::Selection::Ids(ids) => {
+            let mut seen = std::collections::HashSet::new();
+            Ok(ids.iter().copied().filter(|&id| seen.insert(id)).collect())
+        }
+        wred_server::Selection::Query(q) => {
+            let filter = q.parse::<wred_server::query::Query>()?;
+            let logs = data.logs.lock().unwrap();
+            // Like `get_logs`, match on what the caller is allowed to see.
+            let mut ids = logs
+                .iter()
+                .filter(|&(&id, v)| {
+                    filter.matches(&data.mask_partial(data.partial(id, v), identity))
+                })
+                .map(|(&id, _)| id)
+                .collect::<Vec<_>>();
+            drop(logs);
+            // The map iterates in no particular order, and results come back in this one.
+            ids.sort_unstable();
+            Ok(ids)
+        }
+    }
+}
+
+/// Outcome for one log of a bulk operation that returned whether the log existed.
+fn bulk_result(id: u64, result: std::io::Result<bool>) -> wred_server::BulkResult {
+    let error = match result {
+        Ok(true) => None,
+        Ok(false) => Some("No such log".to_owned()),
+        Err(e) => Some(e.to_string()),
+    };
+    wred_server::BulkResult { id, error }
+}
+
+/// Saves every selected log.
+#[utoipa::path(
+    context_path = "/api/v1",
+    request_body(content(
+        (wred_server::Selection = "application/json"),
+        (wred_server::Selection = "application/x-postcard"),
+    )),
+    responses((status = 200, description = "The outcome for each selected log", content(
+        (Vec<wred_server::BulkResult> = "application/json"),
+        (Vec<wred_server::BulkResult> = "application/x-postcard"),
+    )), (status = 400), (status = 401), (status = 403)),
+    security(("token" = ["save"]))
+)]
+#[post("/bulk/save", wrap = "Require(Scope::Save)")]
+#[allow(clippy::future_not_send)]
+async fn bulk_save(
+    req: HttpRequest,
+    body: Encoded<wred_server::Selection>,
+    data: web::Data<super::state::AppState>,
+    identity: web::ReqData<Identity>,
+) -> HttpResponse {
+    let ids = match select(&data, &identity, &body.0) {
+        Ok(v) => v,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    let mut results = Vec::with_capacity(ids.len());
+    for id in ids {
+        results.push(bulk_result(id, data.save_log(id).await));
+    }
+    Encoded(results).respond_to(&req)
+}
+
+/// Removes every selected log from memory and disk.
+#[utoipa::path(
+    context_path = "/api/v1",
+    request_body(content(
+        (wred_server::Selection = "application/json"),
+        (wred_server::Selection = "application/x-postcard"),
+    )),
+    responses((status = 200, description = "The outcome for each selected log", content(
+        (Vec<wred_server::BulkResult> = "application/json"),
+        (Vec<wred_server::BulkResult> = "application/x-postcard"),
+    )), (status = 400), (status = 401), (status = 403)),
+    security(("token" = ["delete"]))
+)]
+#[post("/bulk/delete", wrap = "Require(Scope::Delete)")]
+#[allow(clippy::future_not_send)]
+async fn bulk_delete(
+    req: HttpRequest,
+    body: Encoded<wred_server::Selection>,
+    data: web::Data<super::state::AppState>,
+    identity: web::ReqData<Identity>,
+) -> HttpResponse {
+    let ids = match select(&data, &identity, &body.0) {
+        Ok(v) => v,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    let mut results = Vec::with_capacity(ids.len());
+    for id in ids {
+        results.push(bulk_result(id, data.remove_log(id).await));
+    }
+    Encoded(results).respond_to(&req)
+}
+
+/// Adds and removes tags on every selected log. Tags in both `add` and `remove` are removed.
+#[utoipa::path(
+    context_path = "/api/v1",
+    request_body(content(
+        (wred_server::BulkTags = "application/json"),
+        (wred_server::BulkTags = "application/x-postcard"),
+    )),
+    responses((status = 200, description = "The outcome for each selected log", content(
+        (Vec<wred_server::BulkResult> = "application/json"),
+        (Vec<wred_server::BulkResult> = "application/x-postcard"),
+    )), (status = 400), (status = 401), (status = 403)),
+    security(("token" = ["save"]))
+)]
+#[post("/bulk/tag", wrap = "Require(Scope::Save)")]
+#[allow(clippy::future_not_send)]
+async fn bulk_tag(
+    req: HttpRequest,
+    body: Encoded<wred_server::BulkTags>,
+    data: web::Data<super::state::AppState>,
+    identity: web::ReqData<Identity>,
+) -> HttpResponse {
+    let body = body.0;
+    let ids = match select(&data, &identity, &body.select) {
+        Ok(v) => v,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    let mut results = Vec::with_capacity(ids.len());
+    for id in ids {
+        let found = {
+            let mut logs = data.logs.lock().unwrap();
+            let found = logs.get_mut(&id).map(|v| {
+                v.meta.tags.extend(body.add.iter().cloned());
+                v.meta.tags.retain(|tag| !body.remove.contains(tag));
+                data.notify(wred_server::LogEvent::Session(data.partial(id, v)));
+            });
+            drop(logs);
+            found.is_some()
+        };
+        let result = match found {
+            true if data.is_saved(id) => data.write_log(id).await,
+            found => Ok(found),
+        };
+        results.push(bulk_result(id, result));
+    }
+    Encoded(results).respond_to(&req)
+}
+
+/// Moves every selected log to another triage status.
+#[utoipa::path(
+    context_path = "/api/v1",
+    request_body(content(
+        (wred_server::BulkStatus = "application/json"),
+        (wred_server::BulkStatus = "application/x-postcard"),
+    )),
+    responses((status = 200, description = "The outcome for each selected log", content(
+        (Vec<wred_server::BulkResult> = "application/json"),
+        (Vec<wred_server::BulkResult> = "application/x-postcard"),
+    )), (status = 400), (status = 401), (status = 403)),
+    security(("token" = ["save"]))
+)]
+#[post("/bulk/status", wrap = "Require(Scope::Save)")]
+#[allow(clippy::future_not_send)]
+async fn bulk_status(
+    req: HttpRequest,
+    body: Encoded<wred_server::BulkStatus>,
+    data: web::Data<super::state::AppState>,
+    identity: web::ReqData<Identity>,
+) -> HttpResponse {
+    let body = body.0;
+    let ids = match select(&data, &identity, &body.select) {
+        Ok(v) => v,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    let mut results = Vec::with_capacity(ids.len());
+    for id in ids {
+        if let Err(e) = check_status(&data, id, body.status) {
+            results.push(wred_server::BulkResult { id, error: Some(e) });
+            continue;
+        }
+        let change = wred_server::TriageChange::Status(body.status);
+        let result = match data.record_triage(id, &identity, change) {
+            Some(_) if data.is_saved(id) => data.write_log(id).await,
+            recorded => Ok(recorded.is_some()),
+        };
+        results.push(bulk_result(id, result));
+    }
+    Encoded(results).respond_to(&req)
+}
+
+/// Every baseline, with the key sessions are matched to it by.
+#[utoipa::path(
+    context_path = "/api/v1",
+    responses((status = 200, content(
+        (Vec<wred_server::Baseline> = "application/json"),
+        (Vec<wred_server::Baseline> = "application/x-postcard"),
+    )), (status = 401), (status = 403))
+)]
+#[get("/baselines", wrap = "Require(Scope::Read)")]
+#[allow(clippy::unused_async, clippy::future_not_send)]
+async fn get_baselines(req: HttpRequest, data: web::Data<super::state::AppState>) -> HttpResponse {
+    let logs = data.logs.lock().unwrap();
+    let mut baselines = logs
+        .iter()
+        .filter(|(_, v)| v.is_baseline)
+        .filter_map(|(&id, v)| {
+            Some(wred_server::Baseline {
+                id,
+                key: v.key.clone()?,
+            })
+        })
+        .collect::<Vec<_>>();
+    drop(logs);
+    baselines.sort_unstable_by(|a, b| a.key.cmp(&b.key));
+    Encoded(baselines).respond_to(&req)
+}
+
+/// Makes a saved log the baseline for its key, replacing any other. The key is detected from
+/// the log unless given. Sessions with the same key are compared against it again.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("id" = u64, Path)),
+    request_body(content(
+        (Option<wred_server::BaselineKey> = "application/json"),
+        (Option<wred_server::BaselineKey> = "application/x-postcard"),
+    )),
+    responses((status = 200, description = "The baseline's key", content(
+        (wred_server::BaselineKey = "application/json"),
+        (wred_server::BaselineKey = "application/x-postcard"),
+    )), (status = 400), (status = 401), (status = 403), (status = 404)),
+    security(("token" = ["save"]))
+)]
+#[put("/{id:[[:digit:]]+}/baseline", wrap = "Require(Scope::Save)")]
+#[allow(clippy::future_not_send)]
+async fn set_baseline(
+    req: HttpRequest,
+    path: web::Path<u64>,
+    body: Encoded<Option<wred_server::BaselineKey>>,
+    data: web::Data<super::state::AppState>,
+) -> actix_web::Result<HttpResponse> {
+    let id = path.into_inner();
+    if !data.is_saved(id) {
+        let found = data.logs.lock().unwrap().contains_key(&id);
+        return Ok(if found {
+            HttpResponse::BadRequest().body("Only saved logs can be baselines")
         } else {
-            Ok(HttpResponse::NotFound().finish())
+            HttpResponse::NotFound().finish()
+        });
+    }
+    let (key, replaced) = {
+        let mut logs = data.logs.lock().unwrap();
+        let Some(v) = logs.get(&id) else {
+            return Ok(HttpResponse::NotFound().finish());
+        };
+        let Some(key) = body
+            .0
+            .or_else(|| crate::facts::baseline_key(&data.extractors.extract(&v.data)))
+        else {
+            return Ok(HttpResponse::BadRequest().body("No GPU device ID and OS version found"));
+        };
+        let replaced = logs
+            .iter_mut()
+            .filter(|(&other, v)| other != id && v.is_baseline && v.key.as_ref() == Some(&key))
+            .map(|(&other, v)| {
+                v.is_baseline = false;
+                data.notify(wred_server::LogEvent::Session(data.partial(other, v)));
+                other
+            })
+            .collect::<Vec<_>>();
+        let v = logs.get_mut(&id).unwrap();
+        v.key = Some(key.clone());
+        v.is_baseline = true;
+        v.regression = None;
+        data.notify(wred_server::LogEvent::Session(data.partial(id, v)));
+        drop(logs);
+        (key, replaced)
+    };
+
+    let state = data.clone().into_inner();
+    let analyzed = {
+        let key = key.clone();
+        web::block(move || crate::analyzer::analyze_key(&state, &key)).await?
+    };
+    let changed = std::iter::once(id)
+        .chain(replaced)
+        .chain(analyzed)
+        .collect();
+    crate::analyzer::persist(&data, changed).await?;
+    Ok(Encoded(key).respond_to(&req))
+}
+
+/// Stops a log being a baseline, dropping the comparisons made against it.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("id" = u64, Path)),
+    responses((status = 200), (status = 401), (status = 403), (status = 404)),
+    security(("token" = ["save"]))
+)]
+#[delete("/{id:[[:digit:]]+}/baseline", wrap = "Require(Scope::Save)")]
+async fn unset_baseline(
+    path: web::Path<u64>,
+    data: web::Data<super::state::AppState>,
+) -> std::io::Result<HttpResponse> {
+    let id = path.into_inner();
+    let changed = {
+        let mut logs = data.logs.lock().unwrap();
+        match logs.get_mut(&id) {
+            None => return Ok(HttpResponse::NotFound().finish()),
+            Some(v) if !v.is_baseline => return Ok(HttpResponse::Ok().finish()),
+            Some(v) => v.is_baseline = false,
         }
-    } else {
-        Ok(HttpResponse::Unauthorized().finish())
+        let changed = logs
+            .iter_mut()
+            .filter(|(&other, v)| {
+                other == id || v.regression.as_ref().is_some_and(|v| v.baseline == id)
+            })
+            .map(|(&other, v)| {
+                v.regression = None;
+                data.notify(wred_server::LogEvent::Session(data.partial(other, v)));
+                other
+            })
+            .collect::<Vec<_>>();
+        drop(logs);
+        changed
+    };
+    crate::analyzer::persist(&data, changed).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Error and warning lines of a log that its baseline doesn't have.
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(("id" = u64, Path)),
+    responses((status = 200, content(
+        (wred_server::Regression = "application/json"),
+        (wred_server::Regression = "application/x-postcard"),
+    )), (status = 401), (status = 403), (status = 404, description = "No such log, or no baseline matches it"))
+)]
+#[get("/{id:[[:digit:]]+}/regression", wrap = "Require(Scope::Read)")]
+#[allow(clippy::unused_async, clippy::future_not_send)]
+async fn get_regression(
+    req: HttpRequest,
+    path: web::Path<u64>,
+    data: web::Data<super::state::AppState>,
+) -> HttpResponse {
+    let id = path.into_inner();
+    let regression = data
+        .logs
+        .lock()
+        .unwrap()
+        .get(&id)
+        .and_then(|v| v.regression.clone());
+    regression.map_or_else(
+        || HttpResponse::NotFound().finish(),
+        |v| Encoded(v).respond_to(&req),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::StatusCode, test::TestRequest, App};
+
+    use super::*;
+    use crate::state::{AppState, ServerConfig};
+
+    fn slice(since: Option<u64>, range: &str) -> Option<(u64, u64, bool)> {
+        let range = (!range.is_empty()).then(|| range.parse().unwrap());
+        LogSlice::new(10, since, range).map(|v| (v.start, v.end, v.partial))
+    }
+
+    #[test]
+    fn slices_since() {
+        assert_eq!(slice(None, ""), Some((0, 10, false)));
+        assert_eq!(slice(Some(4), ""), Some((4, 10, false)));
+        assert_eq!(slice(Some(10), ""), Some((10, 10, false)));
+        assert_eq!(slice(Some(11), ""), None);
+        // `since` wins over `Range`.
+        assert_eq!(slice(Some(4), "bytes=0-1"), Some((4, 10, false)));
+    }
+
+    #[test]
+    fn slices_ranges() {
+        assert_eq!(slice(None, "bytes=2-4"), Some((2, 5, true)));
+        assert_eq!(slice(None, "bytes=7-"), Some((7, 10, true)));
+        assert_eq!(slice(None, "bytes=-3"), Some((7, 10, true)));
+        assert_eq!(slice(None, "bytes=5-100"), Some((5, 10, true)));
+        assert_eq!(slice(None, "bytes=10-"), None);
+        // Only a single range is honoured.
+        assert_eq!(slice(None, "bytes=0-1,4-5"), Some((0, 10, false)));
+    }
+
+    #[actix_web::test]
+    async fn shared_links() {
+        let state = AppState::new(ServerConfig::default(), vec![], vec![], b"key".to_vec());
+        let log = wred_server::LogEntry::new(0, "127.0.0.1:1".parse().unwrap(), "a\nb\nc\n".into());
+        state.logs.lock().unwrap().insert(1, log);
+        let state = web::Data::new(state);
+        let app = actix_web::test::init_service(
+            App::new().app_data(state.clone()).service(get_shared_log),
+        )
+        .await;
+        let get = |uri: String| {
+            let req = TestRequest::get().uri(&uri).to_request();
+            actix_web::test::call_service(&app, req)
+        };
+
+        let expires = unix_time() + 60;
+        let lines = Some("2-3".parse().unwrap());
+        let sig = state.share_signature(1, expires, lines);
+        let res = get(format!("/shared/1?expires={expires}&lines=2-3&sig={sig}")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(actix_web::test::read_body(res).await, "b\nc\n");
+
+        // The signature covers the lines and expiry.
+        let res = get(format!("/shared/1?expires={expires}&lines=1-3&sig={sig}")).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let res = get(format!(
+            "/shared/1?expires={}&lines=2-3&sig={sig}",
+            expires + 1
+        ))
+        .await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let expired = unix_time() - 1;
+        let sig = state.share_signature(1, expired, None);
+        let res = get(format!("/shared/1?expires={expired}&sig={sig}")).await;
+        assert_eq!(res.status(), StatusCode::GONE);
+
+        // Bumping the generation revokes links signed before.
+        let sig = state.share_signature(1, expires, None);
+        state
+            .share_generation
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        let res = get(format!("/shared/1?expires={expires}&sig={sig}")).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
     }
 }
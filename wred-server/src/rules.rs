@@ -0,0 +1,111 @@
+//! Rules that flag lines of incoming logs, like `failed` or a register read error, so nobody
+//! has to scan for them by eye.
+
+use std::path::Path;
+
+use regex::RegexSet;
+use serde::{Deserialize, Serialize};
+use wred_server::{Flag, FlagCount, LogEntry, RuleHits, Severity};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rule {
+    pub pattern: String,
+    pub severity: Severity,
+    pub label: String,
+    /// Where to read about what a matching line means.
+    #[serde(default)]
+    pub link: Option<String>,
+}
+
+impl Rule {
+    fn new(pattern: &str, severity: Severity, label: &str) -> Self {
+        Self {
+            pattern: pattern.to_owned(),
+            severity,
+            label: label.to_owned(),
+            link: None,
+        }
+    }
+}
+
+/// Used when the config doesn't name a rule file.
+pub fn default_rules() -> Vec<Rule> {
+    vec![
+        Rule::new(r"(?i)\bpanic", Severity::Error, "panic"),
+        Rule::new(r"(?i)\bfail(ed|ure)?\b", Severity::Error, "failed"),
+        Rule::new(r"(?i)\btime[ d]?out\b", Severity::Warning, "timeout"),
+    ]
+}
+
+pub async fn load_rule_file(path: &Path) -> std::io::Result<Vec<Rule>> {
+    let s = tokio::fs::read_to_string(path).await?;
+    ron::de::from_str(&s).map_err(std::io::Error::other)
+}
+
+#[derive(Debug)]
+pub struct Rules {
+    set: RegexSet,
+    rules: Vec<Rule>,
+}
+
+impl Rules {
+    /// # Panics
+    ///
+    /// If a pattern isn't a valid regex.
+    pub fn new(rules: Vec<Rule>) -> Self {
+        let set = RegexSet::new(rules.iter().map(|v| &v.pattern))
+            .unwrap_or_else(|e| panic!("Bad rule pattern: {e}"));
+        Self { set, rules }
+    }
+
+    /// Scans the complete lines of `v` added since it was last scanned. Returns whether any
+    /// of them matched.
+    pub fn scan(&self, v: &mut LogEntry) -> bool {
+        let RuleHits { hits, end, lines } = &mut v.hits;
+        let Some(last) = v.data.get(*end..).and_then(|v| v.rfind('\n')) else {
+            return false;
+        };
+        let found = hits.len();
+        for line in v.data[*end..=*end + last].lines() {
+            *lines += 1;
+            hits.extend(
+                self.set
+                    .matches(line)
+                    .into_iter()
+                    .map(|rule| (rule, *lines)),
+            );
+        }
+        *end += last + 1;
+        hits.len() > found
+    }
+
+    /// How many lines each rule matched, for rules that matched any.
+    pub fn counts(&self, hits: &RuleHits) -> Vec<FlagCount> {
+        let mut counts = vec![0; self.rules.len()];
+        for &(rule, _) in &hits.hits {
+            counts[rule] += 1;
+        }
+        self.rules
+            .iter()
+            .zip(counts)
+            .filter(|&(_, count)| count > 0)
+            .map(|(rule, count)| FlagCount {
+                label: rule.label.clone(),
+                severity: rule.severity,
+                count,
+            })
+            .collect()
+    }
+
+    pub fn flags<'a>(&'a self, hits: &'a RuleHits) -> impl Iterator<Item = Flag> + 'a {
+        hits.hits.iter().map(|&(rule, line)| {
+            let rule = &self.rules[rule];
+            Flag {
+                line,
+                label: rule.label.clone(),
+                severity: rule.severity,
+                link: rule.link.clone(),
+            }
+        })
+    }
+}
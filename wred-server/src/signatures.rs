@@ -0,0 +1,329 @@
+//! Known issues, matched against sessions as they change so triagers see the resolution
+//! instead of working it out again. Signatures are kept in `signatures.ron` in the log
+//! directory, and the ID the next one gets in `signature-next-id`, so IDs of removed ones
+//! aren't handed out again.
+
+use std::{collections::HashSet, path::PathBuf};
+
+use regex::{Regex, RegexBuilder};
+use wred_server::{query::Query, LogEvent, NewSignature, PatternHits, Signature};
+
+use crate::state::AppState;
+
+/// Most lines a drafted signature takes patterns from.
+const DRAFT_PATTERNS: usize = 3;
+
+#[derive(Debug)]
+struct Compiled {
+    signature: Signature,
+    patterns: Vec<Regex>,
+    conditions: Query,
+}
+
+impl Compiled {
+    fn new(signature: Signature) -> Result<Self, String> {
+        if signature.title.trim().is_empty() {
+            return Err("A signature needs a title".to_owned());
+        }
+        if signature.patterns.is_empty() {
+            return Err("A signature needs at least one pattern".to_owned());
+        }
+        let patterns = signature
+            .patterns
+            .iter()
+            .map(|v| {
+                RegexBuilder::new(v)
+                    .multi_line(true)
+                    .build()
+                    .map_err(|e| format!("Bad pattern {v:?}: {e}"))
+            })
+            .collect::<Result<_, _>>()?;
+        let conditions = signature.conditions.parse()?;
+        Ok(Self {
+            signature,
+            patterns,
+            conditions,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Signatures {
+    list: Vec<Compiled>,
+    /// Bumped when signatures are added, so logs are matched against them from the start.
+    generation: u64,
+    next_id: u64,
+}
+
+impl Signatures {
+    pub fn list(&self) -> Vec<Signature> {
+        self.list.iter().map(|v| v.signature.clone()).collect()
+    }
+
+    /// Adds the patterns `text` has to `found`.
+    fn find(&self, text: &str, found: &mut HashSet<(u64, usize)>) {
+        for v in &self.list {
+            for (i, pattern) in v.patterns.iter().enumerate() {
+                let key = (v.signature.id, i);
+                if !found.contains(&key) && pattern.is_match(text) {
+                    found.insert(key);
+                }
+            }
+        }
+    }
+
+    /// IDs of the signatures a log matches, given the patterns it has.
+    fn matching(
+        &self,
+        partial: &wred_server::LogEntryPartial,
+        found: &HashSet<(u64, usize)>,
+    ) -> Vec<u64> {
+        self.list
+            .iter()
+            .filter(|v| {
+                v.conditions.matches(partial)
+                    && (0..v.patterns.len()).all(|i| found.contains(&(v.signature.id, i)))
+            })
+            .map(|v| v.signature.id)
+            .collect()
+    }
+}
+
+impl AppState {
+    fn signatures_path(&self) -> PathBuf {
+        self.config.log_dir.join("signatures.ron")
+    }
+
+    fn signature_next_id_path(&self) -> PathBuf {
+        self.config.log_dir.join("signature-next-id")
+    }
+
+    pub async fn load_signatures(&self) -> std::io::Result<()> {
+        let next_id = match tokio::fs::read_to_string(self.signature_next_id_path()).await {
+            Ok(v) => v.trim().parse().map_err(std::io::Error::other)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => 1,
+            Err(e) => return Err(e),
+        };
+        let s = match tokio::fs::read_to_string(self.signatures_path()).await {
+            Ok(v) => v,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                self.signatures.lock().unwrap().next_id = next_id;
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+        let list = ron::de::from_str::<Vec<Signature>>(&s)
+            .map_err(std::io::Error::other)?
+            .into_iter()
+            .map(Compiled::new)
+            .collect::<Result<_, _>>()
+            .map_err(std::io::Error::other)?;
+        let mut signatures = self.signatures.lock().unwrap();
+        signatures.list = list;
+        signatures.generation += 1;
+        // Signatures saved before the counter was aren't counted in it.
+        signatures.next_id = signatures
+            .list
+            .iter()
+            .map(|v| v.signature.id + 1)
+            .fold(next_id, u64::max);
+        drop(signatures);
+        Ok(())
+    }
+
+    async fn write_signatures(&self) -> std::io::Result<()> {
+        let (list, next_id) = {
+            let signatures = self.signatures.lock().unwrap();
+            let copy = (signatures.list(), signatures.next_id);
+            drop(signatures);
+            copy
+        };
+        let s = ron::ser::to_string_pretty(&list, ron::ser::PrettyConfig::default())
+            .map_err(std::io::Error::other)?;
+        tokio::fs::write(self.signature_next_id_path(), next_id.to_string()).await?;
+        tokio::fs::write(self.signatures_path(), s).await
+    }
+
+    /// Adds a signature. Returns it, or why it's invalid. Logs aren't matched against it until
+    /// they change, or [`Self::match_all`] is called.
+    pub async fn add_signature(
+        &self,
+        new: NewSignature,
+        created_by: String,
+    ) -> std::io::Result<Result<Signature, String>> {
+        let signature = {
+            let mut signatures = self.signatures.lock().unwrap();
+            let id = signatures.next_id;
+            let compiled = match Compiled::new(Signature {
+                id,
+                title: new.title.trim().to_owned(),
+                resolution: new.resolution,
+                patterns: new.patterns,
+                conditions: new.conditions,
+                created_by,
+                drafted_from: new.drafted_from,
+                created_at: crate::state::unix_micros(),
+            }) {
+                Ok(v) => v,
+                Err(e) => return Ok(Err(e)),
+            };
+            let signature = compiled.signature.clone();
+            signatures.list.push(compiled);
+            signatures.generation += 1;
+            signatures.next_id = id + 1;
+            drop(signatures);
+            signature
+        };
+        self.write_signatures().await?;
+        Ok(Ok(signature))
+    }
+
+    /// IDs of the signatures drafted from any of `ids`.
+    pub fn signatures_drafted_from(&self, ids: &HashSet<u64>) -> Vec<u64> {
+        self.signatures
+            .lock()
+            .unwrap()
+            .list
+            .iter()
+            .filter(|v| v.signature.drafted_from.is_some_and(|v| ids.contains(&v)))
+            .map(|v| v.signature.id)
+            .collect()
+    }
+
+    /// Matches every log against the signatures. Returns the IDs of those whose known issues
+    /// changed.
+    pub fn match_all(&self) -> Vec<u64> {
+        let ids = {
+            let logs = self.logs.lock().unwrap();
+            let ids = logs.keys().copied().collect::<Vec<_>>();
+            drop(logs);
+            ids
+        };
+        ids.into_iter()
+            .filter(|&id| self.match_signatures(id))
+            .collect()
+    }
+
+    /// Removes a signature, and it from the logs that matched it. Returns the IDs of those
+    /// logs, or `None` if there's no such signature.
+    pub async fn remove_signature(&self, id: u64) -> std::io::Result<Option<Vec<u64>>> {
+        {
+            let mut signatures = self.signatures.lock().unwrap();
+            let len = signatures.list.len();
+            signatures.list.retain(|v| v.signature.id != id);
+            if signatures.list.len() == len {
+                return Ok(None);
+            }
+        }
+        self.write_signatures().await?;
+        let mut logs = self.logs.lock().unwrap();
+        let changed = logs
+            .iter_mut()
+            .filter(|(_, v)| v.known_issues.contains(&id))
+            .map(|(&log, v)| {
+                v.known_issues.retain(|&v| v != id);
+                self.notify(LogEvent::Session(self.partial(log, v)));
+                log
+            })
+            .collect();
+        drop(logs);
+        Ok(Some(changed))
+    }
+
+    /// Matches log `id` against the signatures. Only the lines added since it was last matched
+    /// are searched, so a pattern spanning those and earlier lines isn't found. Returns whether
+    /// its known issues changed.
+    pub fn match_signatures(&self, id: u64) -> bool {
+        let generation = self.signatures.lock().unwrap().generation;
+        // Only what's new is copied out, so matching doesn't hold up everything else waiting on
+        // the logs.
+        let (partial, hits, tail) = {
+            let mut logs = self.logs.lock().unwrap();
+            let Some(v) = logs.get_mut(&id) else {
+                return false;
+            };
+            if v.pattern_hits.generation != generation || v.data.get(v.pattern_hits.end..).is_none()
+            {
+                v.pattern_hits = PatternHits {
+                    generation,
+                    ..PatternHits::default()
+                };
+            }
+            let copy = (
+                self.partial(id, v),
+                v.pattern_hits.clone(),
+                v.data[v.pattern_hits.end..].to_owned(),
+            );
+            drop(logs);
+            copy
+        };
+        // What the line after the complete ones has is only kept once it's complete.
+        let complete = tail.rfind('\n').map_or(0, |n| n + 1);
+        let (found, matched) = {
+            let signatures = self.signatures.lock().unwrap();
+            if signatures.generation != generation {
+                return false;
+            }
+            let mut found = hits.found;
+            signatures.find(&tail[..complete], &mut found);
+            let mut with_rest = found.clone();
+            signatures.find(&tail[complete..], &mut with_rest);
+            let matched = signatures.matching(&partial, &with_rest);
+            drop(signatures);
+            (found, matched)
+        };
+
+        let mut logs = self.logs.lock().unwrap();
+        let Some(v) = logs.get_mut(&id) else {
+            return false;
+        };
+        // Matched in the meantime.
+        if v.pattern_hits.end != hits.end || v.pattern_hits.generation != generation {
+            return false;
+        }
+        v.pattern_hits.end += complete;
+        v.pattern_hits.found = found;
+        if v.known_issues == matched {
+            return false;
+        }
+        v.known_issues = matched;
+        self.notify(LogEvent::Session(self.partial(id, v)));
+        drop(logs);
+        true
+    }
+
+    /// A signature for log `id` to start from: its title, and patterns for the first lines
+    /// the rules flagged. Returns `None` if there's no such log.
+    pub fn draft_signature(&self, id: u64) -> Option<NewSignature> {
+        let logs = self.logs.lock().unwrap();
+        let v = logs.get(&id)?;
+        let mut patterns = Vec::<String>::new();
+        let mut lines = v.data.lines();
+        let mut number = 0;
+        for flag in self.rules.flags(&v.hits) {
+            if patterns.len() == DRAFT_PATTERNS {
+                break;
+            }
+            if flag.line <= number {
+                continue;
+            }
+            let Some(line) = lines.nth((flag.line - number - 1) as usize) else {
+                break;
+            };
+            number = flag.line;
+            let message = wred_server::parse::parse_line(flag.line, line).message;
+            let pattern = regex::escape(message.trim());
+            if !pattern.is_empty() && !patterns.contains(&pattern) {
+                patterns.push(pattern);
+            }
+        }
+        let title = v.meta.title.clone();
+        drop(logs);
+        Some(NewSignature {
+            title,
+            patterns,
+            drafted_from: Some(id),
+            ..NewSignature::default()
+        })
+    }
+}
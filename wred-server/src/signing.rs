@@ -0,0 +1,164 @@
+use std::fmt::Write;
+
+use hmac::{Hmac, KeyInit, Mac};
+use sha2::Sha256;
+use tokio::io::AsyncWriteExt;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// The key server-issued signatures are made with: `signing_key` from the config, or one
+/// generated on first run and kept in `signing-key` in the log directory. Never `secret`, which
+/// goes out as a bearer token.
+pub async fn load_key(config: &crate::state::ServerConfig) -> std::io::Result<Vec<u8>> {
+    if !config.signing_key.is_empty() {
+        return Ok(config.signing_key.as_bytes().to_vec());
+    }
+    let path = config.log_dir.join("signing-key");
+    match tokio::fs::read_to_string(&path).await {
+        Ok(v) if v.trim().is_empty() => {
+            return Err(std::io::Error::other(format!(
+                "{} is empty",
+                path.display()
+            )));
+        }
+        Ok(v) => return Ok(v.trim().as_bytes().to_vec()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    let mut key = [0; 32];
+    getrandom::fill(&mut key).map_err(std::io::Error::other)?;
+    let key = to_hex(&key);
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    options.open(&path).await?.write_all(key.as_bytes()).await?;
+    Ok(key.into_bytes())
+}
+
+/// HMAC-SHA256 over `parts`, each length-prefixed so different splits can't collide.
+pub fn sign(key: &[u8], parts: &[&[u8]]) -> [u8; 32] {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC takes keys of any size");
+    for part in parts {
+        mac.update(&(part.len() as u64).to_le_bytes());
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().into()
+}
+
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut s, v| {
+        let _e = write!(s, "{v:02x}");
+        s
+    })
+}
+
+/// Stable pseudonym for an IP, so logs from one machine still group together.
+pub fn pseudonym(key: &[u8], ip: std::net::IpAddr) -> String {
+    let octets = match ip {
+        std::net::IpAddr::V4(v) => v.octets().to_vec(),
+        std::net::IpAddr::V6(v) => v.octets().to_vec(),
+    };
+    format!("anon-{}", to_hex(&sign(key, &[b"pseudonym", &octets])[..4]))
+}
+
+/// Token handed to whoever submitted log `id`, granting access to that log alone. It's derived
+/// from the ID, so nothing has to be stored to check it.
+pub fn claim_token(key: &[u8], id: u64) -> String {
+    let mac = sign(key, &[b"claim", &id.to_le_bytes()]);
+    format!("claim.{id}.{}", to_hex(&mac[..16]))
+}
+
+/// The log ID a claim token is for, if it's genuine.
+pub fn verify_claim_token(key: &[u8], token: &str) -> Option<u64> {
+    let (id, _) = token.strip_prefix("claim.")?.split_once('.')?;
+    let id = id.parse().ok()?;
+    constant_time_eq(claim_token(key, id).as_bytes(), token.as_bytes()).then_some(id)
+}
+
+/// Signature of a share link. Bumping `generation` invalidates every link signed before.
+pub fn share_signature(
+    key: &[u8],
+    generation: u64,
+    id: u64,
+    expires: u64,
+    lines: Option<wred_server::LineRange>,
+) -> String {
+    let lines = lines.map(|v| v.to_string()).unwrap_or_default();
+    let mac = sign(
+        key,
+        &[
+            b"share",
+            &generation.to_le_bytes(),
+            &id.to_le_bytes(),
+            &expires.to_le_bytes(),
+            lines.as_bytes(),
+        ],
+    );
+    to_hex(&mac[..16])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &[u8] = b"key";
+
+    #[test]
+    fn claim_tokens_verify() {
+        let token = claim_token(KEY, 42);
+        assert!(token.starts_with("claim.42."));
+        assert_eq!(verify_claim_token(KEY, &token), Some(42));
+        assert_eq!(verify_claim_token(b"other", &token), None);
+
+        let forged = token.replacen("claim.42.", "claim.43.", 1);
+        assert_eq!(verify_claim_token(KEY, &forged), None);
+        let mut tampered = token.clone();
+        let last = if tampered.ends_with('0') { "1" } else { "0" };
+        tampered.replace_range(tampered.len() - 1.., last);
+        assert_eq!(verify_claim_token(KEY, &tampered), None);
+        assert_eq!(verify_claim_token(KEY, &token[..token.len() - 1]), None);
+        assert_eq!(verify_claim_token(KEY, "claim.x.00"), None);
+        assert_eq!(verify_claim_token(KEY, ""), None);
+    }
+
+    #[test]
+    fn share_signatures_cover_everything() {
+        let lines = Some("2-3".parse().unwrap());
+        let sig = share_signature(KEY, 0, 1, 100, lines);
+        assert_eq!(sig, share_signature(KEY, 0, 1, 100, lines));
+        for other in [
+            share_signature(b"other", 0, 1, 100, lines),
+            share_signature(KEY, 1, 1, 100, lines),
+            share_signature(KEY, 0, 2, 100, lines),
+            share_signature(KEY, 0, 1, 101, lines),
+            share_signature(KEY, 0, 1, 100, Some("2-4".parse().unwrap())),
+            share_signature(KEY, 0, 1, 100, None),
+        ] {
+            assert_ne!(sig, other);
+        }
+    }
+
+    #[test]
+    fn parts_are_delimited() {
+        assert_ne!(sign(KEY, &[b"ab", b"c"]), sign(KEY, &[b"a", b"bc"]));
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"ab"));
+        assert_eq!(to_hex(&[0, 0xab, 0x10]), "00ab10");
+    }
+
+    #[test]
+    fn pseudonyms_are_stable() {
+        let a = "10.0.0.1".parse().unwrap();
+        let b = "10.0.0.2".parse().unwrap();
+        assert_eq!(pseudonym(KEY, a), pseudonym(KEY, a));
+        assert_ne!(pseudonym(KEY, a), pseudonym(KEY, b));
+        assert_ne!(pseudonym(KEY, a), pseudonym(b"other", a));
+        assert!(pseudonym(KEY, a).starts_with("anon-"));
+    }
+}
@@ -1,22 +1,272 @@
 use std::{
     collections::HashMap,
     path::PathBuf,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
 };
 
 use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use wred_server::Submitter;
+
+use crate::auth::{ApiToken, Identity, Scope};
+
+/// How submitter addresses are shown to callers without the `Admin` scope.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AddrVisibility {
+    #[default]
+    Full,
+    Pseudonymized,
+    Hidden,
+}
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct ServerConfig {
     pub ip: String,
     pub api_port: u16,
     pub logger_port: u16,
+    /// Accepted as a bearer token with the admin scope. Prefer `tokens`.
+    #[serde(default)]
     pub secret: String,
     pub log_dir: PathBuf,
+    #[serde(default)]
+    pub tokens: Vec<ApiToken>,
+    /// RON list of more tokens, so they can be managed outside the main config.
+    #[serde(default)]
+    pub token_file: Option<PathBuf>,
+    /// Require a token with the `Read` scope to list or read logs.
+    #[serde(default)]
+    pub require_read_auth: bool,
+    #[serde(default)]
+    pub public_addr: AddrVisibility,
+    /// Key for pseudonyms and other server-issued signatures. If empty, one is generated and
+    /// kept in `signing-key` in the log directory.
+    #[serde(default)]
+    pub signing_key: String,
+    /// Write session details, such as the claim token, back over logger connections.
+    #[serde(default)]
+    pub logger_reply: bool,
+    /// Applied to lines before diffing logs, when asked to normalize.
+    #[serde(default = "crate::diff::default_normalizers")]
+    pub diff_normalizers: Vec<crate::diff::Normalizer>,
+    /// Pull facts about the hardware and OS out of logs. The `device_id` and `os_version` facts
+    /// are what logs are matched against baselines by.
+    #[serde(default = "crate::facts::default_extractors")]
+    pub extractors: Vec<crate::facts::Extractor>,
+    /// RON list of rules flagging lines of incoming logs. Built-in rules are used if unset.
+    #[serde(default)]
+    pub rule_file: Option<PathBuf>,
+    /// What to redact from logs, and when.
+    #[serde(default)]
+    pub redaction: crate::redact::RedactionConfig,
+    /// What to store as the address of new sessions. Sessions are merged by what's stored.
+    #[serde(default)]
+    pub stored_addr: crate::addrs::AddrStorage,
+    /// Days to keep which address a stored pseudonym stands for after it last connected. 0
+    /// keeps nothing.
+    #[serde(default = "default_addr_map_days")]
+    pub addr_map_days: u64,
+    /// Size in bytes past which the audit log is rotated.
+    #[serde(default = "default_audit_max_bytes")]
+    pub audit_max_bytes: u64,
+    /// Rotated audit logs to keep.
+    #[serde(default = "default_audit_keep")]
+    pub audit_keep: usize,
+}
+
+const fn default_addr_map_days() -> u64 {
+    30
+}
+
+const fn default_audit_max_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+const fn default_audit_keep() -> usize {
+    5
 }
 
-#[derive(Debug, Default)]
+pub fn unix_micros() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_micros()
+        .try_into()
+        .unwrap_or(u64::MAX)
+}
+
+#[derive(Debug)]
 pub struct AppState {
     pub config: ServerConfig,
     pub logs: Arc<Mutex<HashMap<u64, wred_server::LogEntry>>>,
+    pub events: broadcast::Sender<wred_server::LogEvent>,
+    pub tokens: Vec<ApiToken>,
+    /// Mixed into share link signatures; bumped to revoke every link handed out so far.
+    pub share_generation: AtomicU64,
+    /// `config.diff_normalizers`, compiled.
+    pub normalizers: Vec<(regex::Regex, String)>,
+    /// `config.extractors`, compiled.
+    pub extractors: crate::facts::Extractors,
+    pub rules: crate::rules::Rules,
+    pub signatures: Mutex<crate::signatures::Signatures>,
+    /// `config.redaction`, compiled.
+    pub redactors: crate::redact::Redactors,
+    pub addr_map: Mutex<crate::addrs::AddrMap>,
+    pub audit: crate::audit::AuditLog,
+    pub erasures: crate::erase::ErasureLog,
+    /// See [`crate::signing::load_key`].
+    key: Vec<u8>,
+}
+
+impl AppState {
+    pub fn new(
+        config: ServerConfig,
+        extra_tokens: Vec<ApiToken>,
+        rules: Vec<crate::rules::Rule>,
+        key: Vec<u8>,
+    ) -> Self {
+        let mut tokens = config.tokens.clone();
+        tokens.extend(extra_tokens);
+        if !config.secret.is_empty() {
+            tokens.push(ApiToken {
+                name: "secret".to_owned(),
+                token: config.secret.clone(),
+                scopes: vec![Scope::Admin],
+                expires: None,
+            });
+        }
+        Self {
+            normalizers: crate::diff::compile(&config.diff_normalizers),
+            extractors: crate::facts::Extractors::new(config.extractors.clone()),
+            rules: crate::rules::Rules::new(rules),
+            signatures: Mutex::default(),
+            redactors: crate::redact::Redactors::new(&config.redaction),
+            addr_map: Mutex::default(),
+            audit: crate::audit::AuditLog::default(),
+            erasures: crate::erase::ErasureLog::default(),
+            key,
+            config,
+            logs: Arc::default(),
+            events: broadcast::channel(1024).0,
+            tokens,
+            share_generation: AtomicU64::new(0),
+        }
+    }
+
+    fn share_generation_path(&self) -> PathBuf {
+        self.config.log_dir.join("share-generation")
+    }
+
+    pub async fn load_share_generation(&self) -> std::io::Result<()> {
+        match tokio::fs::read_to_string(self.share_generation_path()).await {
+            Ok(v) => {
+                let v = v.trim().parse().map_err(std::io::Error::other)?;
+                self.share_generation.store(v, Ordering::SeqCst);
+                Ok(())
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Invalidates all share links, persisting the change so it survives restarts.
+    pub async fn rotate_share_generation(&self) -> std::io::Result<u64> {
+        let v = self.share_generation.fetch_add(1, Ordering::SeqCst) + 1;
+        tokio::fs::write(self.share_generation_path(), v.to_string()).await?;
+        Ok(v)
+    }
+
+    pub fn share_signature(
+        &self,
+        id: u64,
+        expires: u64,
+        lines: Option<wred_server::LineRange>,
+    ) -> String {
+        crate::signing::share_signature(
+            self.signing_key(),
+            self.share_generation.load(Ordering::SeqCst),
+            id,
+            expires,
+            lines,
+        )
+    }
+
+    pub fn is_saved(&self, id: u64) -> bool {
+        self.config
+            .log_dir
+            .join(format!("{id}.log"))
+            .try_exists()
+            .unwrap_or_default()
+    }
+
+    pub fn signing_key(&self) -> &[u8] {
+        &self.key
+    }
+
+    pub fn partial(&self, id: u64, v: &wred_server::LogEntry) -> wred_server::LogEntryPartial {
+        wred_server::LogEntryPartial {
+            id,
+            addr: Submitter::Addr(v.addr),
+            last_updated: v.last_updated,
+            is_saved: self.is_saved(id),
+            meta: v.meta.clone(),
+            status: v.triage.status,
+            assignee: v.triage.assignee.clone(),
+            archived: v.archived,
+            key: v.key.clone(),
+            is_baseline: v.is_baseline,
+            regression: v.regression.as_ref().map(wred_server::Regression::summary),
+            flags: self.rules.counts(&v.hits),
+            known_issues: v.known_issues.clone(),
+            facts: v.facts.clone(),
+        }
+    }
+
+    /// Hides or pseudonymises the address in `partial` if `identity` may not see it.
+    pub fn mask_partial(
+        &self,
+        mut partial: wred_server::LogEntryPartial,
+        identity: &Identity,
+    ) -> wred_server::LogEntryPartial {
+        if identity.allows(Scope::Admin) {
+            return partial;
+        }
+        if let Submitter::Addr(addr) = partial.addr {
+            partial.addr = match self.config.public_addr {
+                AddrVisibility::Full => Submitter::Addr(addr),
+                AddrVisibility::Pseudonymized => {
+                    Submitter::Pseudonym(crate::signing::pseudonym(self.signing_key(), addr.ip()))
+                }
+                AddrVisibility::Hidden => Submitter::Hidden,
+            };
+        }
+        partial
+    }
+
+    /// Records a triage change on a log. Returns `None` if there's no such log.
+    pub fn record_triage(
+        &self,
+        id: u64,
+        by: &Identity,
+        change: wred_server::TriageChange,
+    ) -> Option<wred_server::HistoryEntry> {
+        let entry = wred_server::HistoryEntry {
+            at: unix_micros(),
+            by: by.name.clone(),
+            change,
+        };
+        let mut logs = self.logs.lock().unwrap();
+        let v = logs.get_mut(&id)?;
+        v.triage.apply(entry.clone());
+        self.notify(wred_server::LogEvent::Session(self.partial(id, v)));
+        drop(logs);
+        Some(entry)
+    }
+
+    /// Notifies live-tail subscribers, if there are any.
+    pub fn notify(&self, event: wred_server::LogEvent) {
+        let _e = self.events.send(event);
+    }
 }
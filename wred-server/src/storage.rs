@@ -0,0 +1,137 @@
+use serde::{de::DeserializeOwned, Deserialize};
+
+/// The fields of [`wred_server::LogEntry`] every saved log has. Later fields were appended
+/// over time, so older files end early.
+#[derive(Deserialize)]
+struct BaseLogEntry {
+    last_updated: u64,
+    addr: std::net::SocketAddr,
+    data: String,
+}
+
+/// Decodes a field appended to the format later, defaulting it if `data` ends before it.
+fn take_field<T: DeserializeOwned + Default>(data: &[u8]) -> postcard::Result<(T, &[u8])> {
+    if data.is_empty() {
+        Ok((T::default(), data))
+    } else {
+        postcard::take_from_bytes(data)
+    }
+}
+
+/// Decodes a saved `{id}.log`, in the current format or an older one.
+pub fn decode_log(data: &[u8]) -> postcard::Result<(u64, wred_server::LogEntry)> {
+    let ((id, base), rest) = postcard::take_from_bytes::<(u64, BaseLogEntry)>(data)?;
+    let (meta, rest) = take_field(rest)?;
+    let (triage, rest) = take_field(rest)?;
+    let (comments, rest) = take_field::<Vec<wred_server::Comment>>(rest)?;
+    let (archived, rest) = take_field(rest)?;
+    let (received, rest) = take_field(rest)?;
+    let (key, rest) = take_field(rest)?;
+    let (is_baseline, rest) = take_field(rest)?;
+    let (regression, rest) = take_field(rest)?;
+    let (known_issues, rest) = take_field(rest)?;
+    let (facts, rest) = take_field(rest)?;
+    let (redactions, rest) = take_field(rest)?;
+    let (redacted, rest) = take_field(rest)?;
+    let (next_comment_id, _) = take_field(rest)?;
+    // Logs saved before the counter was stored only know the comments they still have.
+    let next_comment_id = comments
+        .iter()
+        .map(|v| v.id + 1)
+        .fold(next_comment_id, u64::max);
+    Ok((
+        id,
+        wred_server::LogEntry {
+            last_updated: base.last_updated,
+            addr: base.addr,
+            data: base.data,
+            meta,
+            triage,
+            comments,
+            archived,
+            received,
+            key,
+            is_baseline,
+            regression,
+            known_issues,
+            facts,
+            redactions,
+            redacted,
+            next_comment_id,
+            parsed: wred_server::parse::ParsedLog::default(),
+            hits: wred_server::RuleHits::default(),
+            analysis: wred_server::Analysis::default(),
+            pattern_hits: wred_server::PatternHits::default(),
+        },
+    ))
+}
+
+impl crate::state::AppState {
+    /// Writes a log to `{id}.log`, redacting it first if the config says to on save. Returns
+    /// `false` if there's no such log.
+    pub async fn write_log(&self, id: u64) -> std::io::Result<bool> {
+        let v = {
+            let logs = self.logs.lock().unwrap();
+            let v = logs.get(&id).map(|v| {
+                if self.redactors.at() != crate::redact::Stage::Save {
+                    return postcard::to_allocvec(&(id, v));
+                }
+                // Only what's written is redacted, so offsets into the log in memory, like
+                // those live tails and `since` go by, stay valid until the server restarts.
+                let mut v = v.clone();
+                v.hits = wred_server::RuleHits::default();
+                self.redact_log(&mut v, true);
+                postcard::to_allocvec(&(id, &v))
+            });
+            drop(logs);
+            v
+        };
+        let Some(v) = v else {
+            return Ok(false);
+        };
+        let v = v.map_err(std::io::Error::other)?;
+        tokio::fs::write(self.config.log_dir.join(format!("{id}.log")), &v).await?;
+        Ok(true)
+    }
+
+    /// Writes a log to disk and tells live-tail subscribers it's now saved. Returns `false` if
+    /// there's no such log.
+    pub async fn save_log(&self, id: u64) -> std::io::Result<bool> {
+        if !self.write_log(id).await? {
+            return Ok(false);
+        }
+        if let Some(v) = self.logs.lock().unwrap().get(&id) {
+            self.notify(wred_server::LogEvent::Session(self.partial(id, v)));
+        }
+        Ok(true)
+    }
+
+    /// Removes a saved log's file, keeping it in memory only. Returns `false` if there's no such
+    /// log.
+    pub async fn unsave_log(&self, id: u64) -> std::io::Result<bool> {
+        if !self.logs.lock().unwrap().contains_key(&id) {
+            return Ok(false);
+        }
+        let path = self.config.log_dir.join(format!("{id}.log"));
+        if path.exists() {
+            tokio::fs::remove_file(path).await?;
+        }
+        if let Some(v) = self.logs.lock().unwrap().get(&id) {
+            self.notify(wred_server::LogEvent::Session(self.partial(id, v)));
+        }
+        Ok(true)
+    }
+
+    /// Removes a log from memory and disk. Returns `false` if there's no such log.
+    pub async fn remove_log(&self, id: u64) -> std::io::Result<bool> {
+        if self.logs.lock().unwrap().remove(&id).is_none() {
+            return Ok(false);
+        }
+        self.notify(wred_server::LogEvent::Removed(id));
+        let path = self.config.log_dir.join(format!("{id}.log"));
+        if path.exists() {
+            tokio::fs::remove_file(path).await?;
+        }
+        Ok(true)
+    }
+}